@@ -0,0 +1,319 @@
+//! Procedural macros for writing Algorithmia algorithms
+//!
+//! Don't depend on this crate directly: enable the `macros` feature of `algorithmia`
+//! and use the attribute as `#[algorithmia::entrypoint]`.
+
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use proc_macro2::TokenStream as TokenStream2;
+use quote::quote;
+use syn::parse::Parser;
+use syn::punctuated::Punctuated;
+use syn::{
+    Error, FnArg, GenericArgument, Ident, ImplItem, ImplItemMethod, Item, ItemFn, ItemImpl,
+    PathArguments, ReturnType, Signature, Token, Type,
+};
+
+/// Generate `main` for an algorithm from its `apply` function or from an `impl` block
+///
+/// See `algorithmia::entrypoint` for documentation.
+#[proc_macro_attribute]
+pub fn entrypoint(args: TokenStream, item: TokenStream) -> TokenStream {
+    let expanded = parse_args(args.into()).and_then(|args| match syn::parse::<Item>(item)? {
+        Item::Fn(apply) => expand_fn(&args, apply),
+        Item::Impl(imp) => expand_impl(&args, imp),
+        item => Err(Error::new_spanned(
+            item,
+            "#[entrypoint] must be applied to an `apply` function, \
+                 or to an `impl` block with `load` and `apply` functions",
+        )),
+    });
+    expanded.unwrap_or_else(|err| err.to_compile_error()).into()
+}
+
+struct Args {
+    // Print a JSON schema of the input and output when run with `--schema`
+    schema: bool,
+}
+
+fn parse_args(args: TokenStream2) -> Result<Args, Error> {
+    let idents = Punctuated::<Ident, Token![,]>::parse_terminated.parse2(args)?;
+    let mut parsed = Args { schema: false };
+    for ident in idents {
+        if ident == "schema" {
+            parsed.schema = true;
+        } else {
+            return Err(Error::new_spanned(
+                ident,
+                "unknown #[entrypoint] option, expected `schema`",
+            ));
+        }
+    }
+    Ok(parsed)
+}
+
+// Types in the signature of `apply`
+struct Apply {
+    input: Type,
+    context: bool,
+    output: Type,
+    error: Type,
+}
+
+fn expand_fn(args: &Args, item: ItemFn) -> Result<TokenStream2, Error> {
+    let apply = parse_apply(&item.sig, false)?;
+    let name = &item.sig.ident;
+    let (input, output, error) = (&apply.input, &apply.output, &apply.error);
+    let run = if apply.context {
+        quote!(run_with_context)
+    } else {
+        quote!(run)
+    };
+    let schema = schema(args, &apply);
+
+    Ok(quote! {
+        #item
+
+        fn main() {
+            #schema
+            ::algorithmia::handler::entrypoint::#run::<#input, #output, #error, _>(#name)
+        }
+    })
+}
+
+fn expand_impl(args: &Args, item: ItemImpl) -> Result<TokenStream2, Error> {
+    if let Some((_, path, _)) = &item.trait_ {
+        return Err(Error::new_spanned(
+            path,
+            "#[entrypoint] must be applied to an inherent `impl` block, not a trait impl",
+        ));
+    }
+    if !item.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &item.generics,
+            "#[entrypoint] cannot be applied to a generic `impl` block",
+        ));
+    }
+
+    let load = find_fn(&item, "load")?;
+    let load_error = parse_load(&load.sig)?;
+    let apply = parse_apply(&find_fn(&item, "apply")?.sig, true)?;
+    let self_ty = &item.self_ty;
+    let (input, output, error) = (&apply.input, &apply.output, &apply.error);
+    let schema = schema(args, &apply);
+
+    Ok(quote! {
+        #item
+
+        fn main() {
+            #schema
+            ::algorithmia::handler::entrypoint::load_and_run::<
+                #input, #output, #error, #self_ty, #load_error, _, _
+            >(<#self_ty>::load, |input, state| <#self_ty>::apply(state, input))
+        }
+    })
+}
+
+fn find_fn<'a>(item: &'a ItemImpl, name: &str) -> Result<&'a ImplItemMethod, Error> {
+    item.items
+        .iter()
+        .filter_map(|item| match item {
+            ImplItem::Method(method) if method.sig.ident == name => Some(method),
+            _ => None,
+        })
+        .next()
+        .ok_or_else(|| {
+            Error::new(
+                item.impl_token.span,
+                format!("#[entrypoint] `impl` block must define `fn {}`", name),
+            )
+        })
+}
+
+// Returns the error type of `fn load() -> Result<Self, E>`
+fn parse_load(sig: &Signature) -> Result<Type, Error> {
+    if let Some(arg) = sig.inputs.first() {
+        return Err(Error::new_spanned(arg, "`load` cannot take any arguments"));
+    }
+    let (_, error) = result_types(sig)?;
+    Ok(error)
+}
+
+fn parse_apply(sig: &Signature, method: bool) -> Result<Apply, Error> {
+    if !sig.generics.params.is_empty() {
+        return Err(Error::new_spanned(
+            &sig.generics,
+            "`apply` cannot be generic: its input and output types determine how requests are decoded",
+        ));
+    }
+    if let Some(asyncness) = &sig.asyncness {
+        return Err(Error::new_spanned(
+            asyncness,
+            "`apply` cannot be async: return a future and use `handler::run_async` instead",
+        ));
+    }
+
+    let mut inputs = sig.inputs.iter();
+    if method {
+        match inputs.next() {
+            Some(FnArg::Receiver(receiver)) if receiver.reference.is_some() => (),
+            _ => {
+                return Err(Error::new_spanned(
+                    &sig.inputs,
+                    "`apply` must take `&self` or `&mut self` followed by the input",
+                ))
+            }
+        }
+    }
+    let types = inputs
+        .map(|arg| match arg {
+            FnArg::Typed(arg) => Ok((*arg.ty).clone()),
+            FnArg::Receiver(receiver) => Err(Error::new_spanned(
+                receiver,
+                "`apply` must be a function, not a method",
+            )),
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    let (input, context) = match (types.len(), method) {
+        (1, _) => (types[0].clone(), false),
+        (2, false) => match &types[1] {
+            Type::Reference(_) => (types[0].clone(), true),
+            other => {
+                return Err(Error::new_spanned(
+                    other,
+                    "the second argument of `apply` must be `&Context`",
+                ))
+            }
+        },
+        _ => {
+            let expected = if method {
+                "`apply` must take the input after `self`, e.g. `fn apply(&mut self, input: String)`"
+            } else {
+                "`apply` must take the input and optionally a `&Context`, e.g. `fn apply(input: String)`"
+            };
+            return Err(Error::new_spanned(&sig.inputs, expected));
+        }
+    };
+
+    let (output, error) = result_types(sig)?;
+    Ok(Apply {
+        input,
+        context,
+        output,
+        error,
+    })
+}
+
+// Returns `T` and `E` from a return type of `Result<T, E>`
+fn result_types(sig: &Signature) -> Result<(Type, Type), Error> {
+    let name = &sig.ident;
+    let invalid = |span: &dyn quote::ToTokens| {
+        Error::new_spanned(span, format!("`{}` must return `Result<T, E>`", name))
+    };
+    let ty = match &sig.output {
+        ReturnType::Type(_, ty) => ty,
+        ReturnType::Default => return Err(invalid(sig)),
+    };
+    let segment = match &**ty {
+        Type::Path(path) => path.path.segments.last(),
+        _ => None,
+    };
+    let args = match segment {
+        Some(segment) if segment.ident == "Result" => match &segment.arguments {
+            PathArguments::AngleBracketed(args) => &args.args,
+            _ => return Err(invalid(ty)),
+        },
+        _ => return Err(invalid(ty)),
+    };
+    let types: Vec<&Type> = args
+        .iter()
+        .filter_map(|arg| match arg {
+            GenericArgument::Type(ty) => Some(ty),
+            _ => None,
+        })
+        .collect();
+    match types.as_slice() {
+        [output, error] => Ok(((*output).clone(), (*error).clone())),
+        _ => Err(invalid(ty)),
+    }
+}
+
+fn schema(args: &Args, apply: &Apply) -> TokenStream2 {
+    if !args.schema {
+        return TokenStream2::new();
+    }
+    let input = &apply.input;
+    let output = &apply.output;
+    quote! {
+        ::algorithmia::__entrypoint_schema!(#input, #output);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use syn::parse_quote;
+
+    fn tokens(ty: &Type) -> String {
+        quote!(#ty).to_string()
+    }
+
+    #[test]
+    fn test_parse_apply() {
+        let item: ItemFn = parse_quote! {
+            fn apply(input: Vec<u32>, ctx: &Context) -> Result<Output, Box<dyn Error>> {}
+        };
+        let apply = parse_apply(&item.sig, false).unwrap();
+        assert_eq!(tokens(&apply.input), "Vec < u32 >");
+        assert!(apply.context);
+        assert_eq!(tokens(&apply.output), "Output");
+        assert_eq!(tokens(&apply.error), "Box < dyn Error >");
+
+        let method: ImplItemMethod = parse_quote! {
+            fn apply(&mut self, input: String) -> std::result::Result<String, String> {}
+        };
+        let apply = parse_apply(&method.sig, true).unwrap();
+        assert_eq!(tokens(&apply.input), "String");
+        assert!(!apply.context);
+    }
+
+    #[test]
+    fn test_parse_apply_errors() {
+        let invalid: Vec<ItemFn> = vec![
+            parse_quote!(
+                fn apply(input: String) -> String {}
+            ),
+            parse_quote!(
+                fn apply(input: String) {}
+            ),
+            parse_quote!(
+                fn apply<T>(input: T) -> Result<T, String> {}
+            ),
+            parse_quote!(
+                async fn apply(input: String) -> Result<String, String> {}
+            ),
+            parse_quote!(
+                fn apply() -> Result<String, String> {}
+            ),
+            parse_quote!(
+                fn apply(input: String, n: u32) -> Result<String, String> {}
+            ),
+        ];
+        for item in invalid {
+            assert!(parse_apply(&item.sig, false).is_err());
+        }
+
+        let method: ImplItemMethod = parse_quote!(
+            fn apply(input: String) -> Result<String, String> {}
+        );
+        assert!(parse_apply(&method.sig, true).is_err());
+    }
+
+    #[test]
+    fn test_parse_args() {
+        assert!(!parse_args(quote!()).unwrap().schema);
+        assert!(parse_args(quote!(schema)).unwrap().schema);
+        assert!(parse_args(quote!(schemas)).is_err());
+    }
+}
@@ -0,0 +1,31 @@
+use serde::{Deserialize, Serialize};
+use std::error::Error;
+
+#[derive(Deserialize)]
+struct Input {
+    name: String,
+}
+
+#[derive(Serialize)]
+struct Output {
+    msg: String,
+}
+
+struct Greeter {
+    greeting: String,
+}
+
+#[algorithmia::entrypoint]
+impl Greeter {
+    fn load() -> Result<Greeter, Box<dyn Error>> {
+        Ok(Greeter {
+            greeting: "Hello".into(),
+        })
+    }
+
+    fn apply(&mut self, input: Input) -> Result<Output, String> {
+        Ok(Output {
+            msg: format!("{} {}", self.greeting, input.name),
+        })
+    }
+}
@@ -0,0 +1,146 @@
+use super::{AlgoData, AlgoIo, TryFrom};
+use crate::error::{err_msg, Error};
+use ndarray::{ArrayD, ArrayViewD, IxDyn};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use serde_json::Value;
+use std::ops;
+
+/// Wrapper around `ndarray::ArrayD` for converting nested JSON arrays [feature = "ndarray"]
+///
+/// JSON input such as `[[1, 2, 3], [4, 5, 6]]` is decoded into an array of shape `[2, 3]`.
+/// Every row must have the same length. When returned as output, the array is encoded
+/// as nested JSON arrays in the same way.
+///
+/// ```rust,no_run
+/// use algorithmia::algo::Array;
+///
+/// fn apply(matrix: Array<f64>) -> Result<Vec<usize>, String> {
+///     Ok(matrix.shape().to_vec())
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq)]
+pub struct Array<A> {
+    array: ArrayD<A>,
+}
+
+impl<A> Array<A> {
+    /// Unwrap the decoded array
+    pub fn into_inner(self) -> ArrayD<A> {
+        self.array
+    }
+}
+
+impl<A> From<ArrayD<A>> for Array<A> {
+    fn from(array: ArrayD<A>) -> Self {
+        Array { array: array }
+    }
+}
+
+impl<A> ops::Deref for Array<A> {
+    type Target = ArrayD<A>;
+
+    fn deref(&self) -> &ArrayD<A> {
+        &self.array
+    }
+}
+
+impl<A> ops::DerefMut for Array<A> {
+    fn deref_mut(&mut self) -> &mut ArrayD<A> {
+        &mut self.array
+    }
+}
+
+impl<A: DeserializeOwned> TryFrom<AlgoIo> for Array<A> {
+    type Error = Error;
+    fn try_from(val: AlgoIo) -> Result<Self, Self::Error> {
+        let json = match val.data {
+            AlgoData::Text(_) => bail!("Cannot convert text to an array"),
+            AlgoData::Binary(_) => bail!("Cannot convert binary data to an array"),
+            AlgoData::Json(json) => json,
+        };
+
+        let shape = shape(&json);
+        let mut elements = Vec::new();
+        flatten(json, &shape, &mut elements)?;
+        let elements: Vec<A> = serde_json::from_value(Value::Array(elements))
+            .map_err(|err| err_msg(format!("Cannot decode array elements: {}", err)))?;
+        let array = ArrayD::from_shape_vec(IxDyn(&shape), elements)
+            .map_err(|err| err_msg(format!("Cannot build array: {}", err)))?;
+        Ok(Array::from(array))
+    }
+}
+
+impl<A: Serialize> From<Array<A>> for AlgoIo {
+    fn from(array: Array<A>) -> Self {
+        let data = AlgoData::Json(to_json(array.array.view()));
+        AlgoIo { data }
+    }
+}
+
+// The shape implied by the first element at each level of nesting
+fn shape(json: &Value) -> Vec<usize> {
+    let mut shape = Vec::new();
+    let mut json = json;
+    while let Value::Array(items) = json {
+        shape.push(items.len());
+        match items.first() {
+            Some(first) => json = first,
+            None => break,
+        }
+    }
+    shape
+}
+
+// Collect the elements in row-major order, checking that every row matches `shape`
+fn flatten(json: Value, shape: &[usize], elements: &mut Vec<Value>) -> Result<(), Error> {
+    match (json, shape.split_first()) {
+        (Value::Array(items), Some((&len, rest))) if items.len() == len => {
+            for item in items {
+                flatten(item, rest, elements)?;
+            }
+            Ok(())
+        }
+        (Value::Array(_), None) | (_, Some(_)) => {
+            bail!("Cannot convert ragged nested arrays to an array")
+        }
+        (element, None) => {
+            elements.push(element);
+            Ok(())
+        }
+    }
+}
+
+fn to_json<A: Serialize>(view: ArrayViewD<A>) -> Value {
+    if view.ndim() == 0 {
+        let element = view.first().expect("0-dimensional arrays have one element");
+        serde_json::to_value(element).expect("Failed to serialize")
+    } else {
+        Value::Array(view.outer_iter().map(to_json).collect())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_array_roundtrip() {
+        let input = AlgoIo::from(json!([[1, 2, 3], [4, 5, 6]]));
+        let array = Array::<u32>::try_from(input).unwrap();
+        assert_eq!(array.shape(), &[2, 3]);
+        assert_eq!(array[[1, 0]], 4);
+        assert_eq!(AlgoIo::from(array).to_json().unwrap(), "[[1,2,3],[4,5,6]]");
+
+        let empty = Array::<u32>::try_from(AlgoIo::from(json!([]))).unwrap();
+        assert_eq!(empty.shape(), &[0]);
+    }
+
+    #[test]
+    fn test_array_errors() {
+        assert!(Array::<u32>::try_from(AlgoIo::from(json!([[1, 2], [3]]))).is_err());
+        assert!(Array::<u32>::try_from(AlgoIo::from(json!([[1, 2], 3]))).is_err());
+        assert!(Array::<u32>::try_from(AlgoIo::from(json!([1, "two"]))).is_err());
+    }
+}
@@ -47,6 +47,22 @@ impl From<Vec<u8>> for ByteVec {
     }
 }
 
+/// Convert into `bytes::Bytes` without copying [feature = "bytes"]
+#[cfg(feature = "bytes")]
+impl From<ByteVec> for bytes::Bytes {
+    fn from(wrapper: ByteVec) -> bytes::Bytes {
+        bytes::Bytes::from(wrapper.bytes)
+    }
+}
+
+/// Convert from `bytes::Bytes`, copying only if the bytes are shared [feature = "bytes"]
+#[cfg(feature = "bytes")]
+impl From<bytes::Bytes> for ByteVec {
+    fn from(bytes: bytes::Bytes) -> Self {
+        ByteVec::from(Vec::from(bytes))
+    }
+}
+
 impl AsRef<Vec<u8>> for ByteVec {
     fn as_ref(&self) -> &Vec<u8> {
         &self.bytes
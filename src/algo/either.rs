@@ -0,0 +1,52 @@
+use super::{AlgoData, AlgoIo, TryFrom};
+use std::error::Error;
+
+/// Input that is converted to one of two types depending on its content type
+///
+/// Binary input is converted to `R`, and text or JSON input is converted to `L`.
+/// This lets an algorithm accept, for example, either a URL or the raw bytes of a file:
+///
+/// ```rust
+/// use algorithmia::algo::{ByteVec, Either};
+///
+/// fn apply(input: Either<String, ByteVec>) -> Result<usize, String> {
+///     match input {
+///         Either::Left(url) => Ok(url.len()),
+///         Either::Right(bytes) => Ok(bytes.len()),
+///     }
+/// }
+/// ```
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Either<L, R> {
+    /// Converted from text or JSON input
+    Left(L),
+    /// Converted from binary input
+    Right(R),
+}
+
+impl<L, R> TryFrom<AlgoIo> for Either<L, R>
+where
+    L: TryFrom<AlgoIo>,
+    R: TryFrom<AlgoIo>,
+    L::Error: Into<Box<dyn Error>>,
+    R::Error: Into<Box<dyn Error>>,
+{
+    type Error = Box<dyn Error>;
+    fn try_from(val: AlgoIo) -> Result<Self, Self::Error> {
+        match val.data {
+            AlgoData::Binary(_) => R::try_from(val).map(Either::Right).map_err(Into::into),
+            AlgoData::Text(_) | AlgoData::Json(_) => {
+                L::try_from(val).map(Either::Left).map_err(Into::into)
+            }
+        }
+    }
+}
+
+impl<L: Into<AlgoIo>, R: Into<AlgoIo>> From<Either<L, R>> for AlgoIo {
+    fn from(either: Either<L, R>) -> Self {
+        match either {
+            Either::Left(left) => left.into(),
+            Either::Right(right) => right.into(),
+        }
+    }
+}
@@ -0,0 +1,126 @@
+use super::{AlgoData, AlgoIo, TryFrom};
+use crate::error::{err_msg, Error};
+use image::{DynamicImage, ImageFormat};
+use std::io::Cursor;
+use std::ops;
+
+/// Wrapper around `image::DynamicImage` for decoding binary `AlgoIo` [feature = "image"]
+///
+/// Binary input is decoded from any supported image format (e.g. PNG or JPEG).
+/// When returned as output, the image is encoded as PNG, converting images that PNG
+/// can't store (e.g. 32-bit float pixels) to 16-bit channels.
+///
+/// ```rust,no_run
+/// use algorithmia::algo::Image;
+///
+/// fn apply(image: Image) -> Result<String, String> {
+///     Ok(format!("{}x{}", image.width(), image.height()))
+/// }
+/// ```
+#[derive(Debug, Clone)]
+pub struct Image {
+    image: DynamicImage,
+}
+
+impl Image {
+    /// Unwrap the decoded image
+    pub fn into_inner(self) -> DynamicImage {
+        self.image
+    }
+}
+
+impl From<DynamicImage> for Image {
+    fn from(image: DynamicImage) -> Self {
+        Image { image: image }
+    }
+}
+
+impl ops::Deref for Image {
+    type Target = DynamicImage;
+
+    fn deref(&self) -> &DynamicImage {
+        &self.image
+    }
+}
+
+impl ops::DerefMut for Image {
+    fn deref_mut(&mut self) -> &mut DynamicImage {
+        &mut self.image
+    }
+}
+
+impl TryFrom<AlgoIo> for Image {
+    type Error = Error;
+    fn try_from(val: AlgoIo) -> Result<Self, Self::Error> {
+        match val.data {
+            AlgoData::Text(_) => bail!("Cannot convert text to an image"),
+            AlgoData::Json(_) => bail!("Cannot convert JSON to an image"),
+            AlgoData::Binary(bytes) => image::load_from_memory(&bytes)
+                .map(Image::from)
+                .map_err(|err| err_msg(format!("Cannot decode image: {}", err))),
+        }
+    }
+}
+
+/// Encodes the image as PNG
+///
+/// # Panics
+///
+/// Panics if the image can't be encoded (e.g. it is empty): use `Image::to_png` to handle the error.
+impl From<Image> for AlgoIo {
+    fn from(image: Image) -> Self {
+        let png = image.to_png().expect("Failed to encode image as PNG");
+        AlgoIo {
+            data: AlgoData::Binary(png),
+        }
+    }
+}
+
+impl Image {
+    /// Encode the image as PNG, converting pixels that PNG can't store (e.g. 32-bit floats) to 16 bits
+    pub fn to_png(&self) -> Result<Vec<u8>, Error> {
+        let mut png = Cursor::new(Vec::new());
+        // PNG stores 8 or 16 bits per channel, with or without alpha
+        let written = match &self.image {
+            DynamicImage::ImageLuma8(_)
+            | DynamicImage::ImageLumaA8(_)
+            | DynamicImage::ImageRgb8(_)
+            | DynamicImage::ImageRgba8(_)
+            | DynamicImage::ImageLuma16(_)
+            | DynamicImage::ImageLumaA16(_)
+            | DynamicImage::ImageRgb16(_)
+            | DynamicImage::ImageRgba16(_) => self.image.write_to(&mut png, ImageFormat::Png),
+            DynamicImage::ImageRgb32F(_) => {
+                DynamicImage::ImageRgb16(self.image.to_rgb16()).write_to(&mut png, ImageFormat::Png)
+            }
+            _ => DynamicImage::ImageRgba16(self.image.to_rgba16())
+                .write_to(&mut png, ImageFormat::Png),
+        };
+        written.map_err(|err| err_msg(format!("Cannot encode image as PNG: {}", err)))?;
+        Ok(png.into_inner())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use image::{RgbImage, Rgba32FImage};
+
+    #[test]
+    fn test_image_roundtrip() {
+        let image = Image::from(DynamicImage::ImageRgb8(RgbImage::new(3, 2)));
+        let decoded = Image::try_from(AlgoIo::from(image)).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (3, 2));
+
+        let image = Image::from(DynamicImage::ImageRgba32F(Rgba32FImage::new(2, 2)));
+        let decoded = Image::try_from(AlgoIo::from(image)).unwrap();
+        assert!(decoded.as_rgba16().is_some());
+
+        let empty = Image::from(DynamicImage::ImageRgb8(RgbImage::new(0, 0)));
+        assert!(empty.to_png().is_err());
+
+        let err = Image::try_from(AlgoIo::from(crate::algo::ByteVec::from(vec![1, 2, 3])));
+        assert!(err.is_err());
+        assert!(Image::try_from(AlgoIo::from("text")).is_err());
+    }
+}
@@ -17,20 +17,37 @@
 //! # Ok::<(), Box<std::error::Error>>(())
 //! ```
 
-use crate::client::HttpClient;
-use crate::error::{ApiErrorResponse, Error, ResultExt};
+use crate::client::{accept_encoding, decoded_body, HttpClient};
+use crate::error::{ApiError, ApiErrorResponse, Error, ResultExt};
 use crate::Body;
 
+#[cfg(feature = "ndarray")]
+mod array;
 mod bytevec;
-pub use bytevec::ByteVec;
-
+mod either;
+#[cfg(feature = "image")]
+mod image;
+#[cfg(feature = "multipart")]
+mod multipart;
+#[cfg(feature = "ndarray")]
+pub use self::array::Array;
+pub use self::bytevec::ByteVec;
+pub use self::either::Either;
+#[cfg(feature = "image")]
+pub use self::image::Image;
+#[cfg(feature = "multipart")]
+pub use self::multipart::Multipart;
+
+use serde::de::value::SeqDeserializer;
 use serde::de::DeserializeOwned;
 use serde::de::Error as SerdeError;
+use serde::de::{Deserializer, IntoDeserializer, Visitor};
+use serde::forward_to_deserialize_any;
 use serde::{Deserialize, Serialize};
-use serde_json::{self, json, Value};
+use serde_json::{self, json, Map, Value};
 
 use base64;
-use headers_ext::ContentType;
+use headers_ext::ContentType as ContentTypeHeader;
 use mime::{self, Mime};
 #[doc(hidden)]
 pub use reqwest::Response;
@@ -52,6 +69,17 @@ pub struct AlgoIo {
     pub(crate) data: AlgoData,
 }
 
+/// The kind of data held by an `AlgoIo`, as reported in the API's `content_type` field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ContentType {
+    /// Text (sent as `text/plain`)
+    Text,
+    /// JSON (sent as `application/json`)
+    Json,
+    /// Binary data (sent as `application/octet-stream`)
+    Binary,
+}
+
 #[derive(Debug, Clone)]
 pub(crate) enum AlgoData {
     /// Text input or output
@@ -91,6 +119,13 @@ pub struct AlgoMetadata {
     pub alerts: Option<Vec<String>>,
     /// Describes how the ouput's `result` field should be parsed (`text`, `json`, or `binary`)
     pub content_type: String,
+    /// Unique identifier of the API request, useful when contacting support
+    pub request_id: Option<String>,
+    /// Version the algorithm URI resolved to (e.g. `1.2.3`, or a commit hash for unpublished builds)
+    pub version: Option<String>,
+    /// Any other metadata fields returned by the API (e.g. billing details)
+    #[serde(flatten)]
+    pub extra: Map<String, Value>,
     // Placeholder for API stability if additional fields are added later
     #[serde(skip_deserializing)]
     _dummy: (),
@@ -152,20 +187,15 @@ impl Algorithm {
     where
         I: Into<AlgoIo>,
     {
-        let mut res = match input_data.into().data {
-            AlgoData::Text(text) => self.pipe_as(text, mime::TEXT_PLAIN)?,
+        match input_data.into().data {
+            AlgoData::Text(text) => self.pipe_with(text, mime::TEXT_PLAIN),
             AlgoData::Json(json) => {
                 let encoded = serde_json::to_vec(&json)
                     .context("failed to encode algorithm input as JSON")?;
-                self.pipe_as(encoded, mime::APPLICATION_JSON)?
+                self.pipe_with(encoded, mime::APPLICATION_JSON)
             }
-            AlgoData::Binary(bytes) => self.pipe_as(bytes, mime::APPLICATION_OCTET_STREAM)?,
-        };
-
-        let mut res_json = String::new();
-        res.read_to_string(&mut res_json)
-            .context("failed to read algorithm response")?;
-        res_json.parse()
+            AlgoData::Binary(bytes) => self.pipe_with(bytes, mime::APPLICATION_OCTET_STREAM),
+        }
     }
 
     /// Execute an algorithm with a raw JSON string as input.
@@ -174,8 +204,8 @@ impl Algorithm {
     ///   of input, and inferring the content type when making an API call,
     ///   `pipe_json` explicitly sends the provided string with
     ///   `Content-Type: application/json` making no attempt to verify that
-    ///   the input is valid JSON. By contrast, calling `pipe` with a string
-    ///   would send it with `Content-Type: text/plain`.
+    ///   the input is valid JSON. By contrast, calling `pipe` with `AlgoIo::text`
+    ///   would send the string with `Content-Type: text/plain`.
     ///
     /// # Examples
     ///
@@ -187,16 +217,122 @@ impl Algorithm {
     /// let output: Vec<u8> = minmax.pipe_json("[2,3,4]")?.decode()?;
     /// # Ok::<(), Box<std::error::Error>>(())
     pub fn pipe_json(&self, json_input: &str) -> Result<AlgoResponse, Error> {
-        let mut res = self.pipe_as(json_input.to_owned(), mime::APPLICATION_JSON)?;
+        self.pipe_with(json_input.to_owned(), mime::APPLICATION_JSON)
+    }
+
+    /// Execute an algorithm with a request body of an explicit content type.
+    ///
+    /// The body is sent as-is with the given `Content-Type`, which allows calling
+    ///   algorithms that accept formats other than text, JSON, or binary data
+    ///   (e.g. `text/csv`). The response is parsed the same way as for `pipe`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let algo = client.algo("demo/SummarizeCsv/0.1");
+    ///
+    /// let csv = "name,score\nalice,3\nbob,5\n";
+    /// let output: String = algo.pipe_with(csv, mime::TEXT_CSV)?.decode()?;
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    pub fn pipe_with<B>(&self, body: B, content_type: Mime) -> Result<AlgoResponse, Error>
+    where
+        B: Into<Body>,
+    {
+        let res = self.send_pipe(body, content_type, true)?;
 
         let mut res_json = String::new();
-        res.read_to_string(&mut res_json)
+        decoded_body(res)?
+            .read_to_string(&mut res_json)
             .context("failed to read algorithm response")?;
         res_json.parse()
     }
 
+    /// Execute an algorithm with input encoded as MessagePack [feature = "msgpack"]
+    ///
+    /// The input is sent with `Content-Type: application/msgpack`.
+    ///   Structs are encoded as maps with named fields.
+    #[cfg(feature = "msgpack")]
+    pub fn pipe_msgpack<S: Serialize>(&self, input: &S) -> Result<AlgoResponse, Error> {
+        let encoded = rmp_serde::to_vec_named(input)
+            .context("failed to encode algorithm input as MessagePack")?;
+        let content_type = "application/msgpack".parse().expect("valid MIME type");
+        self.pipe_with(encoded, content_type)
+    }
+
+    /// Execute an algorithm with rows of input encoded as CSV [feature = "csv"]
+    ///
+    /// Each row is serialized as a CSV record. If the rows are structs,
+    ///   a header row is written using their field names.
+    ///   The input is sent with `Content-Type: text/csv`.
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// # use serde::Serialize;
+    /// #[derive(Serialize)]
+    /// struct Score<'a> {
+    ///     name: &'a str,
+    ///     score: u32,
+    /// }
+    ///
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let algo = client.algo("demo/SummarizeCsv/0.1");
+    ///
+    /// let rows = vec![Score { name: "alice", score: 3 }, Score { name: "bob", score: 5 }];
+    /// let output: String = algo.pipe_csv(rows)?.decode()?;
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "csv")]
+    pub fn pipe_csv<I>(&self, rows: I) -> Result<AlgoResponse, Error>
+    where
+        I: IntoIterator,
+        I::Item: Serialize,
+    {
+        self.pipe_with(encode_csv(rows)?, mime::TEXT_CSV)
+    }
+
+    /// Execute an algorithm with a `multipart/form-data` request body [feature = "multipart"]
+    ///
+    /// # Examples
+    ///
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// use algorithmia::algo::Multipart;
+    ///
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let algo = client.algo("demo/ResizeImage/0.1");
+    ///
+    /// let form = Multipart::new()
+    ///     .text("width", "200")
+    ///     .file("image", "cat.png", mime::IMAGE_PNG, std::fs::read("cat.png")?);
+    /// let output: String = algo.pipe_multipart(form)?.decode()?;
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    #[cfg(feature = "multipart")]
+    pub fn pipe_multipart(&self, form: Multipart) -> Result<AlgoResponse, Error> {
+        let content_type = form.content_type();
+        self.pipe_with(form.into_bytes(), content_type)
+    }
+
     #[doc(hidden)]
     pub fn pipe_as<B>(&self, input_data: B, content_type: Mime) -> Result<Response, Error>
+    where
+        B: Into<Body>,
+    {
+        self.send_pipe(input_data, content_type, false)
+    }
+
+    // Only accept a compressed response if it will be read through `decoded_body`
+    fn send_pipe<B>(
+        &self,
+        input_data: B,
+        content_type: Mime,
+        decode: bool,
+    ) -> Result<Response, Error>
     where
         B: Into<Body>,
     {
@@ -211,12 +347,15 @@ impl Algorithm {
 
         // We just need the path and query string
         let mut headers = HeaderMap::new();
-        headers.typed_insert(ContentType::from(content_type));
+        headers.typed_insert(ContentTypeHeader::from(content_type));
+        if decode {
+            accept_encoding(&mut headers);
+        }
+        #[cfg(feature = "tracing")]
+        let _span = tracing::info_span!("algorithm", uri = %self.algo_uri).entered();
+        let req = self.client.post(url).headers(headers).body(input_data);
         self.client
-            .post(url)
-            .headers(headers)
-            .body(input_data)
-            .send()
+            .send(req)
             .with_context(|| format!("calling algorithm '{}'", self.algo_uri))
     }
 
@@ -260,7 +399,74 @@ impl AlgoUri {
     }
 }
 
+impl ContentType {
+    /// The name of the content type in the API: `text`, `json`, or `binary`
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ContentType::Text => "text",
+            ContentType::Json => "json",
+            ContentType::Binary => "binary",
+        }
+    }
+}
+
+impl fmt::Display for ContentType {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
 impl AlgoIo {
+    /// Instantiate text input or output
+    ///
+    /// Converting a string with `AlgoIo::from` produces a JSON string instead.
+    ///
+    /// ```rust
+    /// use algorithmia::algo::{AlgoIo, ContentType};
+    ///
+    /// assert_eq!(AlgoIo::text("hello").kind(), ContentType::Text);
+    /// assert_eq!(AlgoIo::from("hello").kind(), ContentType::Json);
+    /// ```
+    pub fn text<S: Into<String>>(text: S) -> AlgoIo {
+        AlgoIo {
+            data: AlgoData::Text(text.into()),
+        }
+    }
+
+    /// Instantiate JSON input or output
+    pub fn json<J: Into<Value>>(json: J) -> AlgoIo {
+        AlgoIo {
+            data: AlgoData::Json(json.into()),
+        }
+    }
+
+    /// Instantiate binary input or output
+    pub fn binary<B: Into<Vec<u8>>>(bytes: B) -> AlgoIo {
+        AlgoIo {
+            data: AlgoData::Binary(bytes.into()),
+        }
+    }
+
+    /// The kind of data held by the `AlgoIo`
+    ///
+    /// ```rust
+    /// # use algorithmia::algo::{AlgoIo, ContentType};
+    /// fn describe(input: &AlgoIo) -> String {
+    ///     match input.kind() {
+    ///         ContentType::Text => format!("text: {}", input.as_string().unwrap()),
+    ///         ContentType::Json => format!("JSON: {}", input.to_json().unwrap()),
+    ///         ContentType::Binary => format!("{} bytes", input.as_bytes().unwrap().len()),
+    ///     }
+    /// }
+    /// ```
+    pub fn kind(&self) -> ContentType {
+        match &self.data {
+            AlgoData::Text(_) => ContentType::Text,
+            AlgoData::Json(_) => ContentType::Json,
+            AlgoData::Binary(_) => ContentType::Binary,
+        }
+    }
+
     /// If the `AlgoIo` is text (or a valid JSON string), returns the associated text
     pub fn as_string(&self) -> Option<&str> {
         match &self.data {
@@ -326,10 +532,124 @@ impl TryFrom<AlgoIo> for AlgoIo {
 impl<D: DeserializeOwned> TryFrom<AlgoIo> for D {
     type Error = Error;
     fn try_from(val: AlgoIo) -> Result<Self, Self::Error> {
-        val.decode()
+        decode_input(val)
+    }
+}
+
+// Like `AlgoIo::decode`, but failures are an `InputError` with the path and expected type of the bad field
+//
+// Text and binary input are deserialized as is rather than as JSON, so a `String` gets the text
+// without a JSON round-trip, and `Vec<u8>` (or `bytes::Bytes`) accepts binary input
+fn decode_input<D: DeserializeOwned>(input: AlgoIo) -> Result<D, Error> {
+    match input.data {
+        AlgoData::Text(text) => {
+            serde_path_to_error::deserialize(RawInput::Text(text)).map_err(input_error)
+        }
+        AlgoData::Json(json) => serde_path_to_error::deserialize(json).map_err(input_error),
+        AlgoData::Binary(bytes) => {
+            serde_path_to_error::deserialize(RawInput::Binary(bytes)).map_err(input_error)
+        }
+    }
+}
+
+fn input_error<E: fmt::Display>(err: serde_path_to_error::Error<E>) -> Error {
+    let path = err.path().to_string();
+    let message = err.inner().to_string();
+    // serde only exposes what it expected in the message, e.g. "invalid type: string \"a\", expected u32"
+    let expected = message
+        .find(", expected ")
+        .map(|pos| message[pos + ", expected ".len()..].to_owned());
+    let message = match &*path {
+        "." => format!("invalid input: {}", message),
+        _ => format!("invalid input at '{}': {}", path, message),
+    };
+    let mut details = json!({ "path": path });
+    if let Some(expected) = expected {
+        details["expected"] = Value::String(expected);
+    }
+    Error::from(ApiError {
+        message: message,
+        error_type: Some("InputError".into()),
+        stacktrace: None,
+        code: None,
+        details: Some(details),
+        _dummy: (),
+    })
+}
+
+// Deserializer for text or binary input
+enum RawInput {
+    Text(String),
+    Binary(Vec<u8>),
+}
+
+impl<'de> Deserializer<'de> for RawInput {
+    type Error = serde::de::value::Error;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            RawInput::Text(text) => visitor.visit_string(text),
+            RawInput::Binary(bytes) => visitor.visit_byte_buf(bytes),
+        }
+    }
+
+    // `Vec<u8>` is deserialized from a sequence of bytes
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        match self {
+            RawInput::Binary(bytes) => visitor.visit_seq(SeqDeserializer::new(bytes.into_iter())),
+            text => text.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, Self::Error> {
+        visitor.visit_some(self)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    // Like a JSON string, text names a unit variant
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, Self::Error> {
+        match self {
+            RawInput::Text(text) => visitor.visit_enum(text.into_deserializer()),
+            binary => binary.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf unit unit_struct tuple tuple_struct map struct identifier ignored_any
     }
 }
 
+#[cfg(feature = "csv")]
+fn encode_csv<I>(rows: I) -> Result<Vec<u8>, Error>
+where
+    I: IntoIterator,
+    I::Item: Serialize,
+{
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for row in rows {
+        writer
+            .serialize(row)
+            .context("failed to encode algorithm input as CSV")?;
+    }
+    writer
+        .into_inner()
+        .map_err(|err| err.into_error())
+        .context("failed to encode algorithm input as CSV")
+}
+
 impl TryFrom<AlgoIo> for ByteVec {
     type Error = Error;
     fn try_from(val: AlgoIo) -> Result<Self, Self::Error> {
@@ -519,6 +839,80 @@ mod tests {
         Algorithmia::client("").unwrap()
     }
 
+    #[test]
+    fn test_either_input() {
+        let text = AlgoIo::text("hello");
+        let binary = AlgoIo::binary(vec![1, 2]);
+        type Input = Either<String, ByteVec>;
+
+        assert_eq!(Input::try_from(text).unwrap(), Either::Left("hello".into()));
+        assert_eq!(
+            Input::try_from(binary).unwrap(),
+            Either::Right(ByteVec::from(vec![1, 2]))
+        );
+        assert!(Input::try_from(AlgoIo::from(json!({"a": 1}))).is_err());
+    }
+
+    #[test]
+    fn test_raw_input_decoding() {
+        let text = r#"{"a": 1}"#;
+        assert_eq!(String::try_from(AlgoIo::text(text)).unwrap(), text);
+        assert_eq!(
+            Option::<String>::try_from(AlgoIo::text(text)).unwrap(),
+            Some(text.to_owned())
+        );
+        assert_eq!(
+            Vec::<u8>::try_from(AlgoIo::binary(vec![0, 255])).unwrap(),
+            vec![0, 255]
+        );
+        assert_eq!(
+            String::try_from(AlgoIo::binary(b"hi".to_vec())).unwrap(),
+            "hi"
+        );
+        assert!(String::try_from(AlgoIo::binary(vec![255])).is_err());
+
+        #[derive(Debug, PartialEq, Deserialize)]
+        enum Mode {
+            Fast,
+        }
+        assert_eq!(Mode::try_from(AlgoIo::text("Fast")).unwrap(), Mode::Fast);
+
+        let err = Value::try_from(AlgoIo::binary(vec![1])).unwrap_err();
+        assert!(format!("{:?}", err).contains("invalid input: invalid type: byte array"));
+    }
+
+    #[cfg(feature = "bytes")]
+    #[test]
+    fn test_bytes_input_decoding() {
+        let input = AlgoIo::binary(vec![1, 2, 3]);
+        assert_eq!(&bytes::Bytes::try_from(input).unwrap()[..], &[1, 2, 3]);
+    }
+
+    #[cfg(feature = "csv")]
+    #[test]
+    fn test_csv_encoding() {
+        #[derive(Serialize)]
+        struct Row {
+            name: &'static str,
+            score: u32,
+        }
+        let rows = vec![
+            Row {
+                name: "alice",
+                score: 3,
+            },
+            Row {
+                name: "bob, jr",
+                score: 5,
+            },
+        ];
+        let encoded = encode_csv(rows).unwrap();
+        assert_eq!(
+            String::from_utf8(encoded).unwrap(),
+            "name,score\nalice,3\n\"bob, jr\",5\n"
+        );
+    }
+
     #[test]
     fn test_algo_without_version_to_url() {
         let mock_client = mock_client();
@@ -565,4 +959,21 @@ mod tests {
         assert_eq!(0.46739511f32, decoded.metadata.duration);
         assert_eq!(expected_result, &*decoded.decode::<Vec<i32>>().unwrap());
     }
+
+    #[test]
+    fn test_metadata_decoding() {
+        let json_output = r#"{"metadata":{"duration":0.5,"content_type":"text","request_id":"req-1","version":"0.2.1","compute_time":1.5,"credits":12.25,"gpu":true},"result":"ok"}"#;
+        let metadata = json_output.parse::<AlgoResponse>().unwrap().metadata;
+        assert_eq!(metadata.request_id.as_ref().unwrap(), "req-1");
+        assert_eq!(metadata.version.as_ref().unwrap(), "0.2.1");
+        assert_eq!(metadata.extra.len(), 3);
+        assert_eq!(metadata.extra["compute_time"], 1.5);
+        assert_eq!(metadata.extra["credits"], 12.25);
+        assert_eq!(metadata.extra["gpu"], true);
+
+        let minimal = r#"{"metadata":{"duration":0.5,"content_type":"void"},"result":null}"#;
+        let metadata = minimal.parse::<AlgoResponse>().unwrap().metadata;
+        assert!(metadata.request_id.is_none() && metadata.version.is_none());
+        assert!(metadata.extra.is_empty());
+    }
 }
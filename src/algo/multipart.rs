@@ -0,0 +1,149 @@
+use mime::Mime;
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+
+/// Builder for a `multipart/form-data` request body [feature = "multipart"]
+///
+/// Each part is a named form field containing either text or a file.
+/// Send the form to an algorithm with `Algorithm::pipe_multipart`.
+///
+/// ```rust
+/// use algorithmia::algo::Multipart;
+///
+/// let form = Multipart::new()
+///     .text("width", "200")
+///     .file("image", "cat.png", mime::IMAGE_PNG, vec![0x89, b'P', b'N', b'G']);
+/// assert_eq!(form.content_type().type_(), mime::MULTIPART);
+/// ```
+#[derive(Debug, Clone)]
+pub struct Multipart {
+    boundary: String,
+    parts: Vec<Part>,
+}
+
+#[derive(Debug, Clone)]
+struct Part {
+    name: String,
+    filename: Option<String>,
+    content_type: Option<Mime>,
+    data: Vec<u8>,
+}
+
+impl Multipart {
+    /// Create an empty form with a random boundary
+    pub fn new() -> Multipart {
+        // `RandomState` is seeded randomly for each instance
+        let random = || RandomState::new().build_hasher().finish();
+        Multipart {
+            boundary: format!("{:016x}{:016x}", random(), random()),
+            parts: Vec::new(),
+        }
+    }
+
+    /// Add a text field
+    pub fn text<N, V>(mut self, name: N, value: V) -> Multipart
+    where
+        N: Into<String>,
+        V: Into<String>,
+    {
+        self.parts.push(Part {
+            name: name.into(),
+            filename: None,
+            content_type: None,
+            data: value.into().into_bytes(),
+        });
+        self
+    }
+
+    /// Add a file field with its file name and content type
+    pub fn file<N, F, D>(mut self, name: N, filename: F, content_type: Mime, data: D) -> Multipart
+    where
+        N: Into<String>,
+        F: Into<String>,
+        D: Into<Vec<u8>>,
+    {
+        self.parts.push(Part {
+            name: name.into(),
+            filename: Some(filename.into()),
+            content_type: Some(content_type),
+            data: data.into(),
+        });
+        self
+    }
+
+    /// The `Content-Type` of the encoded form, including its boundary
+    pub fn content_type(&self) -> Mime {
+        format!("multipart/form-data; boundary={}", self.boundary)
+            .parse()
+            .expect("valid multipart MIME type")
+    }
+
+    /// Encode the form as a request body
+    pub fn into_bytes(self) -> Vec<u8> {
+        let mut body = Vec::new();
+        for part in self.parts {
+            body.extend_from_slice(format!("--{}\r\n", self.boundary).as_bytes());
+            let mut disposition = format!("form-data; name=\"{}\"", escape(&part.name));
+            if let Some(filename) = &part.filename {
+                disposition.push_str(&format!("; filename=\"{}\"", escape(filename)));
+            }
+            body.extend_from_slice(format!("Content-Disposition: {}\r\n", disposition).as_bytes());
+            if let Some(content_type) = &part.content_type {
+                body.extend_from_slice(format!("Content-Type: {}\r\n", content_type).as_bytes());
+            }
+            body.extend_from_slice(b"\r\n");
+            body.extend_from_slice(&part.data);
+            body.extend_from_slice(b"\r\n");
+        }
+        body.extend_from_slice(format!("--{}--\r\n", self.boundary).as_bytes());
+        body
+    }
+}
+
+impl Default for Multipart {
+    fn default() -> Multipart {
+        Multipart::new()
+    }
+}
+
+// Percent-encode the characters that would end a quoted header parameter (as browsers do)
+fn escape(value: &str) -> String {
+    value
+        .replace('"', "%22")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_multipart_encoding() {
+        let form = Multipart::new().text("width", "200").file(
+            "image",
+            "a\"b.png",
+            mime::IMAGE_PNG,
+            vec![1, 2, 3],
+        );
+        let boundary = form.boundary.clone();
+        assert_eq!(
+            form.content_type().get_param(mime::BOUNDARY).unwrap(),
+            &*boundary
+        );
+
+        let mut expected = format!(
+            "--{b}\r\n\
+             Content-Disposition: form-data; name=\"width\"\r\n\r\n\
+             200\r\n\
+             --{b}\r\n\
+             Content-Disposition: form-data; name=\"image\"; filename=\"a%22b.png\"\r\n\
+             Content-Type: image/png\r\n\r\n",
+            b = boundary
+        )
+        .into_bytes();
+        expected.extend_from_slice(&[1, 2, 3]);
+        expected.extend_from_slice(format!("\r\n--{}--\r\n", boundary).as_bytes());
+        assert_eq!(form.into_bytes(), expected);
+    }
+}
@@ -3,12 +3,15 @@
 //! Do not use directly - use the [`Algorithmia`](../struct.Algorithmia.html) struct instead
 use std::str::FromStr;
 use std::sync::Arc;
+#[cfg(feature = "tracing")]
+use std::time::Instant;
 
 use headers_ext::{Authorization, authorization::Credentials, HeaderMapExt, UserAgent};
 use http::header::HeaderMap;
 use http::header::HeaderValue;
-use reqwest::{Client, IntoUrl, Method, RequestBuilder, Url};
+use reqwest::{Client, IntoUrl, Method, RequestBuilder, Response, Url};
 pub use reqwest::Body;
+use std::io::Read;
 
 use crate::error::{Error, ResultExt};
 
@@ -94,6 +97,40 @@ impl HttpClient {
         self.build_request(Method::DELETE, url)
     }
 
+    /// Send a request built by this client
+    ///
+    /// With the `tracing` feature, each request is recorded in a `http_request` span
+    /// with its method, URL, response status, and duration.
+    #[cfg(feature = "tracing")]
+    pub fn send(&self, req: RequestBuilder) -> Result<Response, reqwest::Error> {
+        let req = req.build()?;
+        let span = tracing::debug_span!(
+            "http_request",
+            method = %req.method(),
+            url = %req.url(),
+            status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+        );
+        let _enter = span.enter();
+        let started = Instant::now();
+        let res = self.inner_client.execute(req);
+        span.record("duration_ms", &(started.elapsed().as_millis() as u64));
+        match &res {
+            Ok(res) => {
+                span.record("status", &res.status().as_u16());
+                tracing::debug!("request completed");
+            }
+            Err(err) => tracing::warn!(error = %err, "request failed"),
+        }
+        res
+    }
+
+    /// Send a request built by this client
+    #[cfg(not(feature = "tracing"))]
+    pub fn send(&self, req: RequestBuilder) -> Result<Response, reqwest::Error> {
+        req.send()
+    }
+
     fn build_request(&self, verb: Method, url: Url) -> RequestBuilder {
         let mut headers = HeaderMap::new();
         headers.typed_insert(
@@ -110,17 +147,48 @@ impl HttpClient {
             .headers(headers)
     }
 
-    #[cfg(not(feature = "rust-tls"))]
     fn inner_client() -> Arc<Client> {
-        Arc::new(Client::new())
+        let builder = Client::builder();
+        #[cfg(feature = "rust-tls")]
+        let builder = builder.use_rustls_tls();
+        // Responses are decoded by `decoded_body` instead of reqwest's built-in gzip support
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let builder = builder.gzip(false);
+        Arc::new(builder.build().unwrap())
     }
+}
 
-    #[cfg(feature = "rust-tls")]
-    fn inner_client() -> Arc<Client> {
-        Arc::new(Client::builder().use_rustls_tls().build().unwrap())
+/// Accept the encodings that `decoded_body` can decode
+///
+/// Only requests whose responses are read through `decoded_body` accept compressed
+/// responses: other responses (e.g. directory listings) are parsed as sent.
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub(crate) fn accept_encoding(headers: &mut HeaderMap) {
+    headers.insert(
+        http::header::ACCEPT_ENCODING,
+        HeaderValue::from_static(crate::compression::ACCEPT_ENCODING),
+    );
+}
+
+/// Accept the encodings that `decoded_body` can decode
+#[cfg(not(any(feature = "gzip", feature = "zstd")))]
+pub(crate) fn accept_encoding(_headers: &mut HeaderMap) {}
+
+/// Wrap a response in a decoder matching its `Content-Encoding`
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub(crate) fn decoded_body(res: Response) -> Result<Box<dyn Read + Send>, Error> {
+    match crate::compression::from_headers(res.headers()) {
+        Some(compression) => compression.decode(res),
+        None => Ok(Box::new(res)),
     }
 }
 
+/// Wrap a response in a decoder matching its `Content-Encoding`
+#[cfg(not(any(feature = "gzip", feature = "zstd")))]
+pub(crate) fn decoded_body(res: Response) -> Result<Box<dyn Read + Send>, Error> {
+    Ok(Box::new(res))
+}
+
 impl<'a> From<&'a str> for ApiAuth {
     fn from(api_key: &'a str) -> Self {
         match api_key.len() {
@@ -0,0 +1,174 @@
+//! Transparent compression of API transfers [feature = "gzip" or "zstd"]
+//!
+//! When a compression feature is enabled, file downloads and `Algorithm::pipe_with` advertise
+//! the supported encodings with `Accept-Encoding` and decode responses based on their
+//! `Content-Encoding`.
+
+use crate::error::Error;
+#[cfg(feature = "zstd")]
+use crate::error::ResultExt;
+use http::header::{HeaderMap, CONTENT_ENCODING};
+use std::io::Read;
+
+/// Value of the `Accept-Encoding` header sent with requests whose responses are decoded
+#[cfg(all(feature = "gzip", feature = "zstd"))]
+pub(crate) const ACCEPT_ENCODING: &'static str = "gzip, zstd";
+#[cfg(all(feature = "gzip", not(feature = "zstd")))]
+pub(crate) const ACCEPT_ENCODING: &'static str = "gzip";
+#[cfg(all(feature = "zstd", not(feature = "gzip")))]
+pub(crate) const ACCEPT_ENCODING: &'static str = "zstd";
+
+/// Compression codec used when transferring data
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Compression {
+    /// gzip compression (`.gz`) [feature = "gzip"]
+    #[cfg(feature = "gzip")]
+    Gzip,
+    /// Zstandard compression (`.zst`) [feature = "zstd"]
+    #[cfg(feature = "zstd")]
+    Zstd,
+}
+
+impl Compression {
+    /// Infer the compression from a path's file extension
+    ///
+    /// ```
+    /// # use algorithmia::data::Compression;
+    /// # #[cfg(feature = "gzip")]
+    /// assert_eq!(Compression::from_path("data://.my/logs/out.csv.gz"), Some(Compression::Gzip));
+    /// assert_eq!(Compression::from_path("data://.my/logs/out.csv"), None);
+    /// ```
+    pub fn from_path(path: &str) -> Option<Compression> {
+        match path.rsplitn(2, '.').next() {
+            #[cfg(feature = "gzip")]
+            Some("gz") => Some(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            Some("zst") => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// Parse a `Content-Encoding` header value
+    pub fn from_content_encoding(encoding: &str) -> Option<Compression> {
+        match encoding.trim() {
+            #[cfg(feature = "gzip")]
+            "gzip" | "x-gzip" => Some(Compression::Gzip),
+            #[cfg(feature = "zstd")]
+            "zstd" => Some(Compression::Zstd),
+            _ => None,
+        }
+    }
+
+    /// The `Content-Encoding` header value for this compression
+    pub fn content_encoding(&self) -> &'static str {
+        match *self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => "gzip",
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => "zstd",
+        }
+    }
+
+    /// Wrap a reader so that reading from it yields compressed data
+    pub(crate) fn encode<R>(self, reader: R) -> Result<Box<dyn Read + Send>, Error>
+    where
+        R: Read + Send + 'static,
+    {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Box::new(flate2::read::GzEncoder::new(
+                reader,
+                flate2::Compression::default(),
+            ))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let encoder = zstd::stream::read::Encoder::new(reader, 0)
+                    .context("failed to initialize zstd encoder")?;
+                Ok(Box::new(encoder))
+            }
+        }
+    }
+
+    /// Wrap a reader of compressed data so that reading from it yields decompressed data
+    pub(crate) fn decode<R>(self, reader: R) -> Result<Box<dyn Read + Send>, Error>
+    where
+        R: Read + Send + 'static,
+    {
+        match self {
+            #[cfg(feature = "gzip")]
+            Compression::Gzip => Ok(Box::new(flate2::read::MultiGzDecoder::new(reader))),
+            #[cfg(feature = "zstd")]
+            Compression::Zstd => {
+                let decoder = zstd::stream::read::Decoder::new(reader)
+                    .context("failed to initialize zstd decoder")?;
+                Ok(Box::new(decoder))
+            }
+        }
+    }
+}
+
+/// Determine the compression of a response from its `Content-Encoding`
+pub(crate) fn from_headers(headers: &HeaderMap) -> Option<Compression> {
+    headers
+        .get(CONTENT_ENCODING)
+        .and_then(|val| val.to_str().ok())
+        .and_then(Compression::from_content_encoding)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(compression: Compression) {
+        let text = "hello world ".repeat(100);
+        let mut encoded = Vec::new();
+        compression
+            .encode(std::io::Cursor::new(text.clone().into_bytes()))
+            .unwrap()
+            .read_to_end(&mut encoded)
+            .unwrap();
+        assert!(encoded.len() < text.len());
+
+        let mut decoded = String::new();
+        compression
+            .decode(std::io::Cursor::new(encoded))
+            .unwrap()
+            .read_to_string(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, text);
+    }
+
+    #[cfg(feature = "gzip")]
+    #[test]
+    fn test_gzip_roundtrip() {
+        roundtrip(Compression::Gzip);
+        assert_eq!(
+            Compression::from_path("foo/bar.json.gz"),
+            Some(Compression::Gzip)
+        );
+        assert_eq!(
+            Compression::from_content_encoding("gzip"),
+            Some(Compression::Gzip)
+        );
+    }
+
+    #[cfg(feature = "zstd")]
+    #[test]
+    fn test_zstd_roundtrip() {
+        roundtrip(Compression::Zstd);
+        assert_eq!(
+            Compression::from_path("foo/bar.json.zst"),
+            Some(Compression::Zstd)
+        );
+        assert_eq!(
+            Compression::from_content_encoding("zstd"),
+            Some(Compression::Zstd)
+        );
+    }
+
+    #[test]
+    fn test_uncompressed_path() {
+        assert_eq!(Compression::from_path("foo/bar.json"), None);
+        assert_eq!(Compression::from_content_encoding("br"), None);
+    }
+}
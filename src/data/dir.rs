@@ -50,6 +50,17 @@ pub struct DirectoryDeleted {
     _dummy: (),
 }
 
+/// Response when selectively deleting files with `DataDir::delete_matching`
+#[derive(Debug)]
+pub struct FilesDeleted {
+    /// Data URIs of the files that were deleted (or would be deleted during a dry run)
+    pub deleted: Vec<String>,
+    /// Data URIs of the files or directories that could not be deleted or listed
+    pub failed: Vec<(String, Error)>,
+    // Placeholder for API stability if additional fields are added later
+    _dummy: (),
+}
+
 #[derive(Debug, Deserialize, Serialize)]
 struct FolderItem {
     pub name: String,
@@ -190,11 +201,13 @@ fn get_directory(dir: &DataDir, marker: Option<String>) -> Result<DirectoryShow,
     if let Some(ref m) = marker {
         url.query_pairs_mut().append_pair("marker", m);
     }
+    #[cfg(feature = "tracing")]
+    tracing::debug!(dir = %dir.to_data_uri(), marker = ?marker, "listing directory page");
 
+    let req = dir.client.get(url);
     let mut res = dir
         .client
-        .get(url)
-        .send()
+        .send(req)
         .with_context(|| format!("request error listing directory '{}'", dir.to_data_uri()))
         .and_then(process_http_response)
         .with_context(|| format!("response error listing directory '{}'", dir.to_data_uri()))?;
@@ -215,6 +228,29 @@ fn get_directory(dir: &DataDir, marker: Option<String>) -> Result<DirectoryShow,
     })
 }
 
+fn delete_if_matching<F>(
+    file: DataFileItem,
+    predicate: &mut F,
+    dry_run: bool,
+    res: &mut FilesDeleted,
+) where
+    F: FnMut(&DataFileItem) -> bool,
+{
+    if !predicate(&file) {
+        return;
+    }
+
+    let uri = file.to_data_uri();
+    if dry_run {
+        res.deleted.push(uri);
+        return;
+    }
+    match file.delete() {
+        Ok(_) => res.deleted.push(uri),
+        Err(err) => res.failed.push((uri, err)),
+    }
+}
+
 impl HasDataPath for DataDir {
     #[doc(hidden)]
     fn new(client: HttpClient, path: &str) -> Self {
@@ -295,10 +331,9 @@ impl DataDir {
         };
 
         // POST request
+        let req = self.client.post(parent_url).json(&input_data);
         self.client
-            .post(parent_url)
-            .json(&input_data)
-            .send()
+            .send(req)
             .with_context(|| format!("request error creating directory '{}'", self.to_data_uri()))
             .and_then(process_http_response)
             .with_context(|| {
@@ -329,10 +364,10 @@ impl DataDir {
         }
 
         // Parse response
+        let req = self.client.delete(url);
         let mut res = self
             .client
-            .delete(url)
-            .send()
+            .send(req)
             .with_context(|| format!("request error deleting directory '{}'", self.to_data_uri()))
             .and_then(process_http_response)
             .with_context(|| {
@@ -349,6 +384,75 @@ impl DataDir {
             })
     }
 
+    /// Delete the files in a Directory (and its subdirectories) that match a predicate
+    ///
+    /// Unlike `delete`, failing to delete a file does not stop the walk.
+    /// Instead each failure is reported alongside the successfully deleted files.
+    /// Returns an error if this directory can't be listed at all. Failing to list a
+    /// subdirectory, or a later page of this directory, is reported as a failure of
+    /// that directory, and any files already deleted are still reported.
+    /// With `dry_run` enabled, the matching files are reported without deleting them.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// # use algorithmia::data::HasDataPath;
+    /// # use chrono::{Duration, Utc};
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let my_dir = client.dir(".my/run_outputs");
+    ///
+    /// // Delete logs older than 30 days
+    /// let cutoff = Utc::now() - Duration::days(30);
+    /// let res = my_dir.delete_matching(|f| f.last_modified < cutoff && f.matches("*.log"), false)?;
+    /// for (uri, err) in &res.failed {
+    ///     println!("Error deleting {}: {}", uri, err);
+    /// }
+    /// println!("Deleted {} files", res.deleted.len());
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    pub fn delete_matching<F>(&self, mut predicate: F, dry_run: bool) -> Result<FilesDeleted, Error>
+    where
+        F: FnMut(&DataFileItem) -> bool,
+    {
+        let mut res = FilesDeleted {
+            deleted: Vec::new(),
+            failed: Vec::new(),
+            _dummy: (),
+        };
+
+        self.delete_matching_into(&mut predicate, dry_run, &mut res)?;
+        Ok(res)
+    }
+
+    // Fails only if the first page can't be listed, i.e. before anything was visited
+    fn delete_matching_into<F>(
+        &self,
+        predicate: &mut F,
+        dry_run: bool,
+        res: &mut FilesDeleted,
+    ) -> Result<(), Error>
+    where
+        F: FnMut(&DataFileItem) -> bool,
+    {
+        for (i, entry) in self.list().enumerate() {
+            match entry {
+                Ok(DataItem::Dir(d)) => {
+                    if let Err(err) = d.delete_matching_into(predicate, dry_run, res) {
+                        res.failed.push((d.to_data_uri(), err));
+                    }
+                }
+                Ok(DataItem::File(f)) => delete_if_matching(f, predicate, dry_run, res),
+                Err(err) if i == 0 => return Err(err),
+                Err(err) => {
+                    // Retrying the page would fail again, so give up on the rest of this directory
+                    res.failed.push((self.to_data_uri(), err));
+                    break;
+                }
+            }
+        }
+        Ok(())
+    }
+
     /// Upload a file to an existing Directory
     ///
     /// # Examples
@@ -394,6 +498,47 @@ mod tests {
         Algorithmia::client("").unwrap()
     }
 
+    const LISTING_PAGE: &'static str = "HTTP/1.1 200 OK\r\nConnection: close\r\nX-Data-Type: directory\r\n\
+        Content-Type: application/json\r\n\r\n\
+        {\"files\":[{\"filename\":\"a.log\",\"size\":1,\"last_modified\":\"2020-01-01T00:00:00Z\"},\
+        {\"filename\":\"b.txt\",\"size\":1,\"last_modified\":\"2020-01-01T00:00:00Z\"}],\"marker\":\"page2\"}";
+
+    #[test]
+    fn test_delete_matching_dry_run_stops_on_listing_error() {
+        let client = crate::data::tests::mock_server(vec![
+            LISTING_PAGE,
+            "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let dir = client.dir("data://anowell/foo");
+        let res = dir.delete_matching(|f| f.matches("*.log"), true).unwrap();
+        assert_eq!(res.deleted, vec!["data://anowell/foo/a.log".to_string()]);
+        assert_eq!(res.failed.len(), 1);
+        assert_eq!(res.failed[0].0, "data://anowell/foo");
+    }
+
+    #[test]
+    fn test_delete_matching_fails_if_dir_cannot_be_listed() {
+        let client = crate::data::tests::mock_server(vec![
+            "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let dir = client.dir("data://anowell/foo");
+        assert!(dir.delete_matching(|f| f.matches("*.log"), false).is_err());
+    }
+
+    #[test]
+    fn test_delete_matching_keeps_deleted_on_listing_error() {
+        let client = crate::data::tests::mock_server(vec![
+            LISTING_PAGE,
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+            "HTTP/1.1 500 Internal Server Error\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let dir = client.dir("data://anowell/foo");
+        let res = dir.delete_matching(|f| f.matches("*.log"), false).unwrap();
+        assert_eq!(res.deleted, vec!["data://anowell/foo/a.log".to_string()]);
+        assert_eq!(res.failed.len(), 1);
+        assert_eq!(res.failed[0].0, "data://anowell/foo");
+    }
+
     #[test]
     fn test_to_url() {
         let dir = mock_client().dir("data://anowell/foo");
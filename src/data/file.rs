@@ -12,19 +12,46 @@
 //! ```
 
 use super::{parse_data_uri, parse_headers};
-use crate::client::HttpClient;
+use crate::client::{accept_encoding, decoded_body, HttpClient};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use crate::data::Compression;
 use crate::data::{DataType, HasDataPath};
-use crate::error::{process_http_response, Error, ResultExt};
+use crate::error::{err_msg, process_http_response, Error, ResultExt};
 use crate::Body;
 use chrono::{DateTime, TimeZone, Utc};
-use std::io::{self, Read};
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+use http::header::CONTENT_ENCODING;
+use http::header::{HeaderMap, HeaderValue, ACCEPT_ENCODING, RANGE};
+use reqwest::{Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use std::cmp;
+use std::io::{self, Cursor, Read, Seek, SeekFrom, Write};
+use std::mem;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{sync_channel, Receiver, SyncSender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+
+/// Default number of bytes fetched per range request by `SeekableFileData`
+const DEFAULT_READ_AHEAD: usize = 1024 * 1024;
+
+/// Number of bytes buffered by `FileWriter` before sending a chunk
+const WRITER_CHUNK_SIZE: usize = 1024 * 1024;
+
+/// Number of chunks `FileWriter` may queue before writes block on the upload
+const WRITER_QUEUE_DEPTH: usize = 4;
 
 /// Response and reader when downloading a `DataFile`
 pub struct FileData {
-    /// Size of file in bytes
+    /// Size of the file in bytes as stored (0 if the API did not report it)
+    ///
+    /// When `DataFile::get` decompresses the file, this is its compressed size.
     pub size: u64,
     /// Last modified timestamp
     pub last_modified: DateTime<Utc>,
+    // Unlike `size`, distinguishes an empty file from a response without a length
+    pub(crate) content_length: Option<u64>,
     data: Box<Read>,
 }
 
@@ -57,6 +84,223 @@ impl FileData {
     }
 }
 
+/// Seekable reader when downloading a `DataFile` in byte ranges
+///
+/// Each read outside of the buffered range issues a `Range` request
+/// for at least `read_ahead` bytes starting at the current position.
+pub struct SeekableFileData<'a> {
+    /// Size of file in bytes
+    pub size: u64,
+    /// Last modified timestamp
+    pub last_modified: DateTime<Utc>,
+    file: &'a DataFile,
+    pos: u64,
+    buf: Vec<u8>,
+    buf_start: u64,
+    read_ahead: usize,
+}
+
+impl<'a> SeekableFileData<'a> {
+    /// Builder method to configure the minimum number of bytes fetched per request
+    ///
+    /// Defaults to 1 MiB. Changing it discards any buffered data.
+    pub fn read_ahead(&mut self, bytes: usize) -> &mut SeekableFileData<'a> {
+        self.read_ahead = cmp::max(bytes, 1);
+        self.buf.clear();
+        self
+    }
+
+    fn buffered(&self) -> Option<&[u8]> {
+        let buf_end = self.buf_start + self.buf.len() as u64;
+        if self.pos >= self.buf_start && self.pos < buf_end {
+            Some(&self.buf[(self.pos - self.buf_start) as usize..])
+        } else {
+            None
+        }
+    }
+
+    fn fill_buf(&mut self, min_len: usize) -> Result<(), Error> {
+        let len = cmp::max(self.read_ahead, min_len) as u64;
+        let end = cmp::min(self.pos + len, self.size);
+        self.buf = self.file.get_range(self.pos, end)?;
+        self.buf_start = self.pos;
+        Ok(())
+    }
+}
+
+impl<'a> Read for SeekableFileData<'a> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.pos >= self.size {
+            return Ok(0);
+        }
+        if self.buffered().is_none() {
+            self.fill_buf(buf.len())
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        }
+
+        // A range response that ends before `size` would otherwise look like the end of the file
+        let n = match self.buffered() {
+            Some(available) => {
+                let n = cmp::min(available.len(), buf.len());
+                buf[..n].copy_from_slice(&available[..n]);
+                n
+            }
+            None => {
+                return Err(io::Error::new(
+                    io::ErrorKind::UnexpectedEof,
+                    format!("range response ended at byte {} of {}", self.pos, self.size),
+                ))
+            }
+        };
+        self.pos += n as u64;
+        Ok(n)
+    }
+}
+
+impl<'a> Seek for SeekableFileData<'a> {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        let (base, offset) = match pos {
+            SeekFrom::Start(n) => {
+                self.pos = n;
+                return Ok(n);
+            }
+            SeekFrom::End(n) => (self.size, n),
+            SeekFrom::Current(n) => (self.pos, n),
+        };
+        let new_pos = if offset >= 0 {
+            base.checked_add(offset as u64)
+        } else {
+            base.checked_sub(offset.wrapping_neg() as u64)
+        };
+        match new_pos {
+            Some(n) => {
+                self.pos = n;
+                Ok(n)
+            }
+            None => Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "invalid seek to a negative or overflowing position",
+            )),
+        }
+    }
+}
+
+/// Streaming writer when uploading a `DataFile`
+///
+/// Data is sent to the API in chunks as it is written.
+/// Call `finish` to complete the upload. Dropping the writer
+/// without calling `finish` aborts the upload.
+pub struct FileWriter {
+    buf: Vec<u8>,
+    sender: Option<SyncSender<Vec<u8>>>,
+    // Set when the writer is dropped without calling `finish`
+    aborted: Arc<AtomicBool>,
+    upload: Option<JoinHandle<Result<(), Error>>>,
+}
+
+// Request body that reads the chunks sent by a `FileWriter`
+struct ChunkReader {
+    receiver: Receiver<Vec<u8>>,
+    aborted: Arc<AtomicBool>,
+    current: Cursor<Vec<u8>>,
+}
+
+impl Read for ChunkReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        loop {
+            if self.aborted.load(Ordering::SeqCst) {
+                return Err(io::Error::new(io::ErrorKind::Other, "upload aborted"));
+            }
+            let n = self.current.read(buf)?;
+            if n > 0 || buf.is_empty() {
+                return Ok(n);
+            }
+            match self.receiver.recv() {
+                Ok(bytes) => self.current = Cursor::new(bytes),
+                // Sender was dropped by `finish` (or on abort, which is checked first)
+                Err(_) if !self.aborted.load(Ordering::SeqCst) => return Ok(0),
+                Err(_) => (),
+            }
+        }
+    }
+}
+
+impl FileWriter {
+    /// Complete the upload, waiting for the API to respond
+    pub fn finish(mut self) -> Result<(), Error> {
+        self.send_buf()?;
+        self.sender.take();
+        self.wait_for_upload()
+    }
+
+    fn send_buf(&mut self) -> Result<(), Error> {
+        if self.buf.is_empty() {
+            return Ok(());
+        }
+        let chunk = mem::replace(&mut self.buf, Vec::new());
+        let sent = match &self.sender {
+            Some(sender) => sender.send(chunk).is_ok(),
+            None => false,
+        };
+        if sent {
+            return Ok(());
+        }
+
+        // The request ended early, so surface its error
+        self.sender.take();
+        self.wait_for_upload()
+            .and_then(|_| Err(err_msg("upload completed before all data was sent")))
+    }
+
+    fn wait_for_upload(&mut self) -> Result<(), Error> {
+        match self.upload.take() {
+            Some(handle) => handle
+                .join()
+                .unwrap_or_else(|_| Err(err_msg("upload thread panicked"))),
+            None => Err(err_msg("upload has already completed")),
+        }
+    }
+}
+
+impl Write for FileWriter {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        self.buf.extend_from_slice(buf);
+        if self.buf.len() >= WRITER_CHUNK_SIZE {
+            self.send_buf()
+                .map_err(|err| io::Error::new(io::ErrorKind::Other, err))?;
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.send_buf()
+            .map_err(|err| io::Error::new(io::ErrorKind::Other, err))
+    }
+}
+
+// Doesn't send anything to abort, since the queue may be full if the upload has stalled
+impl Drop for FileWriter {
+    fn drop(&mut self) {
+        // Set before disconnecting, so that the upload sees the abort rather than the end of the data
+        if self.sender.is_some() {
+            self.aborted.store(true, Ordering::SeqCst);
+            self.sender.take();
+        }
+    }
+}
+
+// Headers for requesting the half-open byte range `start..end` without any content encoding
+fn range_headers(start: u64, end: u64) -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    let range = format!("bytes={}-{}", start, end - 1);
+    headers.insert(
+        RANGE,
+        HeaderValue::from_str(&range).expect("range is valid ASCII"),
+    );
+    headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+    headers
+}
+
 /// Algorithmia data file
 pub struct DataFile {
     path: String,
@@ -102,10 +346,47 @@ impl DataFile {
         B: Into<Body>,
     {
         let url = self.to_url()?;
+        let req = self.client.put(url).body(body);
         self.client
+            .send(req)
+            .with_context(|| format!("request error writing file '{}'", self.to_data_uri()))
+            .and_then(process_http_response)
+            .with_context(|| format!("response error writing file '{}'", self.to_data_uri()))?;
+
+        Ok(())
+    }
+
+    /// Compress data while writing it to the Algorithmia Data API [feature = "gzip" or "zstd"]
+    ///
+    /// The upload is sent with a `Content-Encoding` matching the compression,
+    /// and `get` will transparently decompress it.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// # use algorithmia::data::Compression;
+    /// # use std::fs::File;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    ///
+    /// let file = File::open("/path/to/results.csv")?;
+    /// # #[cfg(feature = "gzip")]
+    /// client.file(".my/my_dir/results.csv.gz").put_compressed(file, Compression::Gzip)?;
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    pub fn put_compressed<R>(&self, body: R, compression: Compression) -> Result<(), Error>
+    where
+        R: Read + Send + 'static,
+    {
+        let url = self.to_url()?;
+        let encoded = compression.encode(body)?;
+        let req = self
+            .client
             .put(url)
-            .body(body)
-            .send()
+            .header(CONTENT_ENCODING, compression.content_encoding())
+            .body(Body::new(encoded));
+        self.client
+            .send(req)
             .with_context(|| format!("request error writing file '{}'", self.to_data_uri()))
             .and_then(process_http_response)
             .with_context(|| format!("response error writing file '{}'", self.to_data_uri()))?;
@@ -113,8 +394,62 @@ impl DataFile {
         Ok(())
     }
 
+    /// Create a streaming writer to the Algorithmia Data API
+    ///
+    /// The upload begins immediately and data is sent as it is written.
+    /// Call `finish` on the returned `FileWriter` to complete the upload.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// # use std::io::Write;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let my_file = client.file(".my/my_dir/rows.csv");
+    ///
+    /// let mut writer = my_file.create_writer()?;
+    /// for i in 0..1000 {
+    ///     writeln!(writer, "{},{}", i, i * i)?;
+    /// }
+    /// writer.finish()?;
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    pub fn create_writer(&self) -> Result<FileWriter, Error> {
+        let url = self.to_url()?;
+        let client = self.client.clone();
+        let data_uri = self.to_data_uri();
+        let (sender, receiver) = sync_channel(WRITER_QUEUE_DEPTH);
+        let aborted = Arc::new(AtomicBool::new(false));
+        let body = ChunkReader {
+            receiver,
+            aborted: aborted.clone(),
+            current: Cursor::new(Vec::new()),
+        };
+
+        let upload = thread::spawn(move || {
+            let req = client.put(url).body(Body::new(body));
+            client
+                .send(req)
+                .with_context(|| format!("request error writing file '{}'", data_uri))
+                .and_then(process_http_response)
+                .with_context(|| format!("response error writing file '{}'", data_uri))?;
+            Ok(())
+        });
+
+        Ok(FileWriter {
+            buf: Vec::with_capacity(WRITER_CHUNK_SIZE),
+            sender: Some(sender),
+            aborted: aborted,
+            upload: Some(upload),
+        })
+    }
+
     /// Get a file from the Algorithmia Data API
     ///
+    /// With the `gzip` or `zstd` feature enabled, the file is decompressed
+    /// based on its `Content-Encoding` or else its extension (`.gz` or `.zst`).
+    /// In that case `size` is still the stored (compressed) size of the file.
+    /// Use `get_raw` to read the file exactly as stored.
+    ///
     /// # Examples
     /// ```no_run
     /// # use algorithmia::Algorithmia;
@@ -126,10 +461,40 @@ impl DataFile {
     /// # Ok::<_, Box<std::error::Error>>(())
     /// ```
     pub fn get(&self) -> Result<FileData, Error> {
+        self.get_file(true)
+    }
+
+    /// Get a file from the Algorithmia Data API without decompressing it
+    ///
+    /// Unlike `get`, the data is never decompressed, even with the `gzip` or `zstd`
+    /// feature enabled, so it is exactly `size` bytes long when `size` is known.
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let my_file = client.file(".my/my_dir/logs.csv.gz");
+    ///
+    /// let compressed = my_file.get_raw()?.into_bytes()?;
+    /// # Ok::<_, Box<std::error::Error>>(())
+    /// ```
+    pub fn get_raw(&self) -> Result<FileData, Error> {
+        self.get_file(false)
+    }
+
+    fn get_file(&self, decompress: bool) -> Result<FileData, Error> {
         let url = self.to_url()?;
-        let req = self.client.get(url);
-        let res = req
-            .send()
+        let mut headers = HeaderMap::new();
+        match decompress {
+            true => accept_encoding(&mut headers),
+            false => {
+                headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+            }
+        }
+        let req = self.client.get(url).headers(headers);
+        let res = self
+            .client
+            .send(req)
             .with_context(|| format!("request error downloading file '{}'", self.to_data_uri()))
             .and_then(process_http_response)
             .with_context(|| format!("response error downloading file '{}'", self.to_data_uri()))?;
@@ -142,15 +507,233 @@ impl DataFile {
             }
         }
 
+        let data = match decompress {
+            true => self.decompress(res)?,
+            false => Box::new(res),
+        };
+
         Ok(FileData {
             size: metadata.content_length.unwrap_or(0),
             last_modified: metadata
                 .last_modified
                 .unwrap_or_else(|| Utc.ymd(2015, 3, 14).and_hms(8, 0, 0)),
-            data: Box::new(res),
+            content_length: metadata.content_length,
+            data,
         })
     }
 
+    // Decode the response based on its `Content-Encoding`, falling back to the file extension
+    fn decompress(&self, res: Response) -> Result<Box<dyn Read + Send>, Error> {
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        let path_compression = match crate::compression::from_headers(res.headers()) {
+            Some(_) => None,
+            None => Compression::from_path(&self.path),
+        };
+
+        #[allow(unused_mut)]
+        let mut data = decoded_body(res)?;
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        {
+            if let Some(compression) = path_compression {
+                data = compression.decode(data)?;
+            }
+        }
+        Ok(data)
+    }
+
+    /// Get a file from the Algorithmia Data API and decode it from JSON
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// # use std::collections::HashMap;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let my_file = client.file(".my/my_dir/config.json");
+    ///
+    /// let config: HashMap<String, String> = my_file.get_json()?;
+    /// # Ok::<_, Box<std::error::Error>>(())
+    /// ```
+    pub fn get_json<D: DeserializeOwned>(&self) -> Result<D, Error> {
+        let data = self.get()?;
+        serde_json::from_reader(data)
+            .with_context(|| format!("JSON decoding error reading file '{}'", self.to_data_uri()))
+    }
+
+    /// Encode data as JSON and write it to the Algorithmia Data API
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let my_file = client.file(".my/my_dir/scores.json");
+    ///
+    /// my_file.put_json(&vec![0.5, 0.25, 0.125])?;
+    /// # Ok::<_, Box<std::error::Error>>(())
+    /// ```
+    pub fn put_json<S: Serialize>(&self, data: &S) -> Result<(), Error> {
+        let encoded = serde_json::to_vec(data).with_context(|| {
+            format!("JSON encoding error writing file '{}'", self.to_data_uri())
+        })?;
+        self.put(encoded)
+    }
+
+    /// Get a file from the Algorithmia Data API and decode it from CBOR [feature = "cbor"]
+    #[cfg(feature = "cbor")]
+    pub fn get_cbor<D: DeserializeOwned>(&self) -> Result<D, Error> {
+        let data = self.get()?;
+        serde_cbor::from_reader(data)
+            .with_context(|| format!("CBOR decoding error reading file '{}'", self.to_data_uri()))
+    }
+
+    /// Encode data as CBOR and write it to the Algorithmia Data API [feature = "cbor"]
+    #[cfg(feature = "cbor")]
+    pub fn put_cbor<S: Serialize>(&self, data: &S) -> Result<(), Error> {
+        let encoded = serde_cbor::to_vec(data).with_context(|| {
+            format!("CBOR encoding error writing file '{}'", self.to_data_uri())
+        })?;
+        self.put(encoded)
+    }
+
+    /// Get a file from the Algorithmia Data API and decode it from MessagePack [feature = "msgpack"]
+    #[cfg(feature = "msgpack")]
+    pub fn get_msgpack<D: DeserializeOwned>(&self) -> Result<D, Error> {
+        let data = self.get()?;
+        rmp_serde::from_read(data).with_context(|| {
+            format!(
+                "MessagePack decoding error reading file '{}'",
+                self.to_data_uri()
+            )
+        })
+    }
+
+    /// Encode data as MessagePack and write it to the Algorithmia Data API [feature = "msgpack"]
+    ///
+    /// Structs are encoded as maps with named fields.
+    #[cfg(feature = "msgpack")]
+    pub fn put_msgpack<S: Serialize>(&self, data: &S) -> Result<(), Error> {
+        let encoded = rmp_serde::to_vec_named(data).with_context(|| {
+            format!(
+                "MessagePack encoding error writing file '{}'",
+                self.to_data_uri()
+            )
+        })?;
+        self.put(encoded)
+    }
+
+    /// Get a file from the Algorithmia Data API and decode it from YAML [feature = "yaml"]
+    #[cfg(feature = "yaml")]
+    pub fn get_yaml<D: DeserializeOwned>(&self) -> Result<D, Error> {
+        let data = self.get()?;
+        serde_yaml::from_reader(data)
+            .with_context(|| format!("YAML decoding error reading file '{}'", self.to_data_uri()))
+    }
+
+    /// Encode data as YAML and write it to the Algorithmia Data API [feature = "yaml"]
+    #[cfg(feature = "yaml")]
+    pub fn put_yaml<S: Serialize>(&self, data: &S) -> Result<(), Error> {
+        let encoded = serde_yaml::to_vec(data).with_context(|| {
+            format!("YAML encoding error writing file '{}'", self.to_data_uri())
+        })?;
+        self.put(encoded)
+    }
+
+    /// Open a file from the Algorithmia Data API as a seekable reader
+    ///
+    /// Unlike `get`, this does not download the file up front. Instead, the
+    /// returned reader fetches byte ranges as they are read, which is useful
+    /// for formats that need random access (e.g. zip archives).
+    ///
+    /// # Examples
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// # use std::io::{Read, Seek, SeekFrom};
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let my_file = client.file(".my/my_dir/archive.zip");
+    ///
+    /// let mut reader = my_file.open_seekable()?;
+    /// reader.read_ahead(64 * 1024);
+    /// reader.seek(SeekFrom::End(-22))?;
+    /// let mut footer = Vec::new();
+    /// reader.read_to_end(&mut footer)?;
+    /// # Ok::<_, Box<std::error::Error>>(())
+    /// ```
+    pub fn open_seekable(&self) -> Result<SeekableFileData, Error> {
+        let url = self.to_url()?;
+        let req = self.client.head(url);
+        let res = self
+            .client
+            .send(req)
+            .with_context(|| format!("request error opening file '{}'", self.to_data_uri()))
+            .and_then(process_http_response)
+            .with_context(|| format!("response error opening file '{}'", self.to_data_uri()))?;
+
+        let metadata = parse_headers(res.headers())?;
+        match metadata.data_type {
+            DataType::File => (),
+            DataType::Dir => {
+                bail!("expected API response with data type 'file', received 'directory'")
+            }
+        }
+
+        let size = match metadata.content_length {
+            Some(size) => size,
+            None => bail!(
+                "cannot open file '{}' for seeking: API response missing content length",
+                self.to_data_uri()
+            ),
+        };
+
+        Ok(SeekableFileData {
+            size,
+            last_modified: metadata
+                .last_modified
+                .unwrap_or_else(|| Utc.ymd(2015, 3, 14).and_hms(8, 0, 0)),
+            file: self,
+            pos: 0,
+            buf: Vec::new(),
+            buf_start: 0,
+            read_ahead: DEFAULT_READ_AHEAD,
+        })
+    }
+
+    // Fetch the bytes in the half-open range `start..end`
+    fn get_range(&self, start: u64, end: u64) -> Result<Vec<u8>, Error> {
+        if start >= end {
+            return Ok(Vec::new());
+        }
+
+        let url = self.to_url()?;
+        let req = self.client.get(url).headers(range_headers(start, end));
+        let mut res = self
+            .client
+            .send(req)
+            .with_context(|| {
+                format!(
+                    "request error downloading range of '{}'",
+                    self.to_data_uri()
+                )
+            })
+            .and_then(process_http_response)
+            .with_context(|| {
+                format!(
+                    "response error downloading range of '{}'",
+                    self.to_data_uri()
+                )
+            })?;
+
+        let mut bytes = Vec::with_capacity((end - start) as usize);
+        if res.status() == StatusCode::PARTIAL_CONTENT {
+            res.read_to_end(&mut bytes)
+        } else {
+            // Server ignored the range header, so skip ahead in the full response
+            io::copy(&mut res.by_ref().take(start), &mut io::sink())
+                .and_then(|_| res.take(end - start).read_to_end(&mut bytes))
+        }
+        .with_context(|| format!("error reading range of '{}'", self.to_data_uri()))?;
+
+        Ok(bytes)
+    }
+
     /// Delete a file from from the Algorithmia Data API
     ///
     /// # Examples
@@ -168,7 +751,8 @@ impl DataFile {
     pub fn delete(&self) -> Result<(), Error> {
         let url = self.to_url()?;
         let req = self.client.delete(url);
-        req.send()
+        self.client
+            .send(req)
             .with_context(|| format!("request error deleting file '{}'", self.to_data_uri()))
             .and_then(process_http_response)
             .with_context(|| format!("response error deleting file '{}'", self.to_data_uri()))?;
@@ -176,3 +760,185 @@ impl DataFile {
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::tests::{mock_server, mock_server_with_requests};
+    use crate::Algorithmia;
+    use std::time::Duration;
+
+    fn mock_client() -> Algorithmia {
+        Algorithmia::client("").unwrap()
+    }
+
+    fn mock_reader<'a>(file: &'a DataFile, contents: &[u8]) -> SeekableFileData<'a> {
+        SeekableFileData {
+            size: contents.len() as u64,
+            last_modified: Utc.ymd(2015, 3, 14).and_hms(8, 0, 0),
+            file: file,
+            pos: 0,
+            buf: contents.to_vec(),
+            buf_start: 0,
+            read_ahead: DEFAULT_READ_AHEAD,
+        }
+    }
+
+    #[test]
+    fn test_seekable_read_from_buffer() {
+        let file = mock_client().file("data://anowell/foo.txt");
+        let mut reader = mock_reader(&file, b"hello world");
+        reader.seek(SeekFrom::Start(6)).unwrap();
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "world");
+    }
+
+    #[test]
+    fn test_get_accept_encoding() {
+        let response =
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nX-Data-Type: file\r\nContent-Length: 5\r\n\r\nhello";
+        let (client, requests) = mock_server_with_requests(vec![response, response]);
+        let file = client.file("data://anowell/foo.txt");
+
+        assert_eq!(file.get_raw().unwrap().into_bytes().unwrap(), b"hello");
+        let request = requests.recv().unwrap();
+        assert_eq!(request.header("accept-encoding"), ["identity"]);
+
+        // Without a compression feature, reqwest picks the encoding (and decodes it)
+        assert_eq!(file.get().unwrap().into_string().unwrap(), "hello");
+        let _request = requests.recv().unwrap();
+        #[cfg(any(feature = "gzip", feature = "zstd"))]
+        assert_eq!(
+            _request.header("accept-encoding"),
+            [crate::compression::ACCEPT_ENCODING]
+        );
+    }
+
+    #[test]
+    fn test_seekable_read_short_range() {
+        let client = mock_server(vec![
+            "HTTP/1.1 206 Partial Content\r\nConnection: close\r\nContent-Length: 3\r\n\r\n wo",
+            "HTTP/1.1 206 Partial Content\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let file = client.file("data://anowell/foo.txt");
+        let mut reader = mock_reader(&file, b"hello");
+        reader.size = 11;
+
+        let mut bytes = Vec::new();
+        let err = reader.read_to_end(&mut bytes).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::UnexpectedEof);
+        assert_eq!(bytes, b"hello wo");
+    }
+
+    #[test]
+    fn test_open_seekable_requires_length() {
+        let client = mock_server(vec![
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nX-Data-Type: file\r\n\r\n",
+        ]);
+        let file = client.file("data://anowell/foo.txt");
+        let err = file.open_seekable().err().unwrap();
+        assert!(err.to_string().contains("missing content length"));
+    }
+
+    #[test]
+    fn test_chunk_reader_reads_until_sender_dropped() {
+        let (sender, receiver) = sync_channel(2);
+        let mut reader = ChunkReader {
+            receiver,
+            aborted: Arc::new(AtomicBool::new(false)),
+            current: Cursor::new(Vec::new()),
+        };
+        sender.send(b"hello ".to_vec()).unwrap();
+        sender.send(b"world".to_vec()).unwrap();
+        drop(sender);
+
+        let mut text = String::new();
+        reader.read_to_string(&mut text).unwrap();
+        assert_eq!(text, "hello world");
+    }
+
+    #[test]
+    fn test_chunk_reader_errors_on_abort() {
+        let (sender, receiver) = sync_channel(2);
+        let aborted = Arc::new(AtomicBool::new(false));
+        let mut reader = ChunkReader {
+            receiver,
+            aborted: aborted.clone(),
+            current: Cursor::new(Vec::new()),
+        };
+        sender.send(b"partial".to_vec()).unwrap();
+        aborted.store(true, Ordering::SeqCst);
+        drop(sender);
+
+        let mut bytes = Vec::new();
+        assert!(reader.read_to_end(&mut bytes).is_err());
+    }
+
+    #[test]
+    fn test_writer_finish_uploads() {
+        let (client, requests) = mock_server_with_requests(vec![
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let mut writer = client
+            .file("data://anowell/foo.txt")
+            .create_writer()
+            .unwrap();
+        writer.write_all(b"hello ").unwrap();
+        writer.flush().unwrap();
+        writer.write_all(b"world").unwrap();
+        writer.finish().unwrap();
+
+        let request = requests.recv().unwrap();
+        assert!(request
+            .head
+            .starts_with("PUT /v1/connector/data/anowell/foo.txt "));
+        assert!(request.complete);
+        assert_eq!(request.body, b"hello world");
+    }
+
+    #[test]
+    fn test_writer_aborts_on_drop() {
+        let (client, requests) = mock_server_with_requests(vec![
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nContent-Length: 0\r\n\r\n",
+        ]);
+        let mut writer = client
+            .file("data://anowell/foo.txt")
+            .create_writer()
+            .unwrap();
+        writer.write_all(b"partial").unwrap();
+        writer.flush().unwrap();
+        // Give the upload time to connect, so that the server sees the aborted request
+        thread::sleep(Duration::from_millis(200));
+        drop(writer);
+
+        let request = requests.recv_timeout(Duration::from_secs(10)).unwrap();
+        assert!(!request.complete);
+    }
+
+    #[test]
+    fn test_writer_drop_does_not_block_on_full_queue() {
+        let (sender, _receiver) = sync_channel(0);
+        let writer = FileWriter {
+            buf: Vec::new(),
+            sender: Some(sender),
+            aborted: Arc::new(AtomicBool::new(false)),
+            upload: None,
+        };
+        let aborted = writer.aborted.clone();
+        drop(writer);
+        assert!(aborted.load(Ordering::SeqCst));
+    }
+
+    #[test]
+    fn test_seekable_seek_positions() {
+        let file = mock_client().file("data://anowell/foo.txt");
+        let mut reader = mock_reader(&file, b"hello world");
+        assert_eq!(reader.seek(SeekFrom::End(-5)).unwrap(), 6);
+        assert_eq!(reader.seek(SeekFrom::Current(-2)).unwrap(), 4);
+        assert_eq!(reader.seek(SeekFrom::Current(3)).unwrap(), 7);
+        assert!(reader.seek(SeekFrom::Current(-8)).is_err());
+        assert_eq!(reader.seek(SeekFrom::Start(20)).unwrap(), 20);
+        assert_eq!(reader.read(&mut [0; 4]).unwrap(), 0);
+    }
+}
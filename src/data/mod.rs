@@ -6,6 +6,8 @@ pub use self::dir::*;
 pub use self::file::*;
 pub use self::object::*;
 pub use self::path::*;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+pub use crate::compression::Compression;
 
 use crate::error::{err_msg, Error};
 use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
@@ -44,6 +46,27 @@ pub struct DataFileItem {
     file: DataFile,
 }
 
+impl DataFileItem {
+    /// Check whether the file name matches a glob pattern
+    ///
+    /// The pattern is matched against the whole basename: `*` matches any sequence
+    /// of characters and `?` matches any single character.
+    ///
+    /// ```no_run
+    /// # use algorithmia::Algorithmia;
+    /// let client = Algorithmia::client("111112222233333444445555566")?;
+    /// let res = client.dir(".my/run_outputs").delete_matching(|f| f.matches("*.log"), true)?;
+    /// println!("Would delete {} logs", res.deleted.len());
+    /// # Ok::<(), Box<std::error::Error>>(())
+    /// ```
+    pub fn matches(&self, pattern: &str) -> bool {
+        match self.basename() {
+            Some(name) => glob_match(pattern, &name),
+            None => false,
+        }
+    }
+}
+
 impl Deref for DataFileItem {
     type Target = DataFile;
     fn deref(&self) -> &DataFile {
@@ -100,6 +123,35 @@ fn parse_headers(headers: &HeaderMap) -> Result<HeaderData, Error> {
     })
 }
 
+// Match `*` and `?` wildcards, backtracking to the most recent `*` on a mismatch
+fn glob_match(pattern: &str, name: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let name: Vec<char> = name.chars().collect();
+    let (mut p, mut n) = (0, 0);
+    let mut star = None;
+    while n < name.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                star = Some((p, n));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == name[n] => {
+                p += 1;
+                n += 1;
+            }
+            _ => match star {
+                Some((star_p, star_n)) => {
+                    p = star_p + 1;
+                    n = star_n + 1;
+                    star = Some((star_p, star_n + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
 fn parse_data_uri(data_uri: &str) -> String {
     match data_uri {
         p if p.contains("://") => p.split_terminator("://").collect::<Vec<_>>().join("/"),
@@ -109,8 +161,118 @@ fn parse_data_uri(data_uri: &str) -> String {
 }
 
 #[cfg(test)]
-mod tests {
-    use super::parse_data_uri;
+pub(crate) mod tests {
+    use super::*;
+    use crate::Algorithmia;
+    use std::io::{BufRead, BufReader, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::mpsc::{channel, Receiver};
+    use std::thread;
+
+    // Serve each response to one connection, in order, from a local HTTP server
+    pub(crate) fn mock_server(responses: Vec<&'static str>) -> Algorithmia {
+        mock_server_with_requests(responses).0
+    }
+
+    // A request received by the mock server: its head (request line and headers) and its body
+    pub(crate) struct MockRequest {
+        pub(crate) head: String,
+        pub(crate) body: Vec<u8>,
+        // Whether the whole body was received before the connection closed
+        pub(crate) complete: bool,
+    }
+
+    impl MockRequest {
+        // Values of the header `name`, in the order they were sent
+        pub(crate) fn header(&self, name: &str) -> Vec<&str> {
+            self.head
+                .lines()
+                .filter_map(|line| {
+                    let mut parts = line.splitn(2, ':');
+                    match (parts.next(), parts.next()) {
+                        (Some(key), Some(value)) if key.eq_ignore_ascii_case(name) => {
+                            Some(value.trim())
+                        }
+                        _ => None,
+                    }
+                })
+                .collect()
+        }
+    }
+
+    // Like `mock_server`, also receiving each request once its response has been sent
+    pub(crate) fn mock_server_with_requests(
+        responses: Vec<&'static str>,
+    ) -> (Algorithmia, Receiver<MockRequest>) {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        let (sender, receiver) = channel();
+        thread::spawn(move || {
+            for response in responses {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let request = read_request(&mut reader);
+                // The client may have given up on the request (e.g. an aborted upload)
+                let _ = reader.get_mut().write_all(response.as_bytes());
+                let _ = sender.send(request);
+            }
+        });
+        (Algorithmia::client_with_url("", &*url).unwrap(), receiver)
+    }
+
+    // Read a request's head and its body (sized by `Content-Length` or chunked), up to EOF
+    fn read_request<R: BufRead>(reader: &mut R) -> MockRequest {
+        let mut head = String::new();
+        let mut line = String::new();
+        while reader.read_line(&mut line).unwrap_or(0) > 0 && line != "\r\n" {
+            head.push_str(&line);
+            line.clear();
+        }
+        let mut request = MockRequest {
+            head: head,
+            body: Vec::new(),
+            complete: true,
+        };
+        if let Some(len) = request.header("content-length").first() {
+            let len = len.parse().unwrap();
+            let _ = reader.by_ref().take(len).read_to_end(&mut request.body);
+            request.complete = request.body.len() as u64 == len;
+        } else if request.header("transfer-encoding") == ["chunked"] {
+            loop {
+                line.clear();
+                let len = match reader.read_line(&mut line) {
+                    Ok(n) if n > 0 => u64::from_str_radix(line.trim(), 16).ok(),
+                    _ => None,
+                };
+                match len {
+                    Some(0) => break,
+                    Some(len) => {
+                        let _ = reader.by_ref().take(len).read_to_end(&mut request.body);
+                        line.clear();
+                        let _ = reader.read_line(&mut line);
+                    }
+                    None => {
+                        request.complete = false;
+                        break;
+                    }
+                }
+            }
+        }
+        request
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("*.log", "run.log"));
+        assert!(glob_match("*.log", ".log"));
+        assert!(!glob_match("*.log", "run.log.gz"));
+        assert!(glob_match("run-??.csv", "run-01.csv"));
+        assert!(!glob_match("run-??.csv", "run-1.csv"));
+        assert!(glob_match("*-*-final*", "a-b-final.txt"));
+        assert!(glob_match("exact", "exact"));
+        assert!(!glob_match("exact", "exact2"));
+        assert!(glob_match("*", ""));
+    }
 
     #[test]
     fn test_parse_protocol() {
@@ -45,8 +45,9 @@ impl DataObject {
     pub fn get_type(&self) -> Result<DataType, Error> {
         let url = self.to_url()?;
         let req = self.client.head(url);
-        let res = req
-            .send()
+        let res = self
+            .client
+            .send(req)
             .with_context(|| format!("request error getting type of '{}'", self.to_data_uri()))
             .and_then(process_http_response)
             .with_context(|| format!("response error getting type of '{}'", self.to_data_uri()))?;
@@ -72,8 +73,9 @@ impl DataObject {
         let metadata = {
             let url = self.to_url()?;
             let req = self.client.head(url);
-            let res = req
-                .send()
+            let res = self
+                .client
+                .send(req)
                 .with_context(|| format!("request error getting type of '{}'", self.to_data_uri()))
                 .and_then(process_http_response)
                 .with_context(|| {
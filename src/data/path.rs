@@ -93,8 +93,8 @@ pub trait HasDataPath {
         let client = self.client();
         let req = client.head(url);
 
-        let res = req
-            .send()
+        let res = client
+            .send(req)
             .with_context(|| format!("checking existence of '{}'", self.to_data_uri()))?;
         match res.status() {
             StatusCode::OK => Ok(true),
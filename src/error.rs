@@ -3,9 +3,10 @@ use crate::client::header::{lossy_header, X_ERROR_MESSAGE};
 use backtrace::Backtrace;
 use reqwest::Response;
 use serde::{Deserialize, Serialize};
-use serde_json;
+use serde_json::{self, Value};
 use std::error::Error as StdError;
 use std::fmt::Display;
+use std::io::Read;
 use std::{fmt, str};
 
 /// Default error type for errors originating in algorithm code
@@ -114,6 +115,16 @@ impl_into_error_kind!(serde_json::error::Error);
 impl_into_error_kind!(reqwest::header::InvalidHeaderValue);
 impl_into_error_kind!(url::ParseError);
 impl_into_error_kind!(base64::DecodeError);
+#[cfg(feature = "cbor")]
+impl_into_error_kind!(serde_cbor::Error);
+#[cfg(feature = "msgpack")]
+impl_into_error_kind!(rmp_serde::decode::Error);
+#[cfg(feature = "msgpack")]
+impl_into_error_kind!(rmp_serde::encode::Error);
+#[cfg(feature = "yaml")]
+impl_into_error_kind!(serde_yaml::Error);
+#[cfg(feature = "csv")]
+impl_into_error_kind!(csv::Error);
 
 impl<T, E> ResultExt<T> for Result<T, E>
 where
@@ -139,7 +150,7 @@ where
 }
 
 /// Error from the Algorithmia API (may be from the algorithm)
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ApiError {
     /// Error message returned from the Algorithmia API
     pub message: String,
@@ -147,6 +158,15 @@ pub struct ApiError {
     pub error_type: Option<String>,
     /// Stacktrace of algorithm exception/panic
     pub stacktrace: Option<String>,
+    /// Machine-readable error code set by the algorithm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// Additional structured details about the error set by the algorithm
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub details: Option<Value>,
+    // Placeholder for API stability if additional fields are added later
+    #[serde(skip)]
+    pub(crate) _dummy: (),
 }
 
 impl Display for ApiError {
@@ -213,8 +233,67 @@ impl ApiError {
             error_type: Some(error_type.into()),
             message: message.into(),
             stacktrace: Some(format!("{:?}", Backtrace::new())),
+            code: None,
+            details: None,
+            _dummy: (),
         }
     }
+
+    /// Builder method to set a machine-readable error code
+    ///
+    /// ```
+    /// use algorithmia::error::ApiError;
+    /// use serde_json::json;
+    ///
+    /// ApiError::new("InputError", "Input missing field 'url'")
+    ///     .with_code("MISSING_FIELD")
+    ///     .with_details(json!({ "field": "url" }));
+    /// ```
+    pub fn with_code<S: Into<String>>(mut self, code: S) -> ApiError {
+        self.code = Some(code.into());
+        self
+    }
+
+    /// Builder method to set additional structured details about the error
+    pub fn with_details(mut self, details: Value) -> ApiError {
+        self.details = Some(details);
+        self
+    }
+}
+
+/// Extension methods to convert errors into structured `ApiError`s
+///
+/// When an algorithm run with `handler::run` returns an `ApiError`, its
+/// `error_type`, `stacktrace`, `code`, and `details` are passed through to the caller.
+///
+/// ```
+/// use algorithmia::error::{AlgoErrorExt, ApiError};
+///
+/// fn parse_count(input: &str) -> Result<u32, ApiError> {
+///     input.parse::<u32>().with_error_code("InputError", "INVALID_COUNT")
+/// }
+///
+/// let err = parse_count("ten").unwrap_err();
+/// assert_eq!(err.error_type.as_ref().unwrap(), "InputError");
+/// assert_eq!(err.code.as_ref().unwrap(), "INVALID_COUNT");
+/// ```
+pub trait AlgoErrorExt<T> {
+    /// Convert the error into an `ApiError` with the specified `error_type` (e.g. `"InputError"`)
+    fn with_error_type(self, error_type: &str) -> Result<T, ApiError>;
+
+    /// Convert the error into an `ApiError` with the specified `error_type` and machine-readable `code`
+    fn with_error_code(self, error_type: &str, code: &str) -> Result<T, ApiError>;
+}
+
+impl<T, E: Display> AlgoErrorExt<T> for Result<T, E> {
+    fn with_error_type(self, error_type: &str) -> Result<T, ApiError> {
+        self.map_err(|err| ApiError::new(error_type.to_string(), err.to_string()))
+    }
+
+    fn with_error_code(self, error_type: &str, code: &str) -> Result<T, ApiError> {
+        self.with_error_type(error_type)
+            .map_err(|err| err.with_code(code))
+    }
 }
 
 impl<S> From<S> for ApiError
@@ -226,6 +305,9 @@ where
             error_type: Some(ALGORITHM_ERROR.into()),
             message: message.into(),
             stacktrace: Some(format!("{:?}", Backtrace::new())),
+            code: None,
+            details: None,
+            _dummy: (),
         }
     }
 }
@@ -253,13 +335,16 @@ pub(crate) fn process_http_response(mut resp: Response) -> Result<Response, Erro
     if status.is_success() {
         Ok(resp)
     } else {
-        let api_err = match resp.json::<ApiErrorResponse>() {
-            Ok(err_res) => Some(err_res.error),
-            Err(_) => match resp.headers().get(X_ERROR_MESSAGE).map(lossy_header) {
+        let api_err = match read_error_body(&mut resp) {
+            Some(err_res) => Some(err_res.error),
+            None => match resp.headers().get(X_ERROR_MESSAGE).map(lossy_header) {
                 Some(message) => Some(ApiError {
                     message,
                     error_type: None,
                     stacktrace: None,
+                    code: None,
+                    details: None,
+                    _dummy: (),
                 }),
                 None => None,
             },
@@ -271,3 +356,22 @@ pub(crate) fn process_http_response(mut resp: Response) -> Result<Response, Erro
         })
     }
 }
+
+// Error bodies of requests that accept compressed responses may be compressed too
+fn read_error_body(resp: &mut Response) -> Option<ApiErrorResponse> {
+    let mut body = Vec::new();
+    resp.read_to_end(&mut body).ok()?;
+    #[cfg(any(feature = "gzip", feature = "zstd"))]
+    {
+        if let Some(compression) = crate::compression::from_headers(resp.headers()) {
+            let mut decoded = Vec::new();
+            compression
+                .decode(std::io::Cursor::new(body))
+                .ok()?
+                .read_to_end(&mut decoded)
+                .ok()?;
+            body = decoded;
+        }
+    }
+    serde_json::from_slice(&body).ok()
+}
@@ -0,0 +1,227 @@
+//! Downloading data files (e.g. models) to a local cache
+
+use crate::data::{DataFile, HasDataPath};
+use crate::error::{err_msg, Error, ResultExt};
+use crate::Algorithmia;
+use std::env;
+use std::fs::{self, File};
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Environment variable that overrides the default cache directory
+const CACHE_DIR_VAR: &'static str = "ALGORITHMIA_ASSET_CACHE";
+
+/// Number of downloads started by this process, which makes their temporary paths unique
+static DOWNLOADS: AtomicUsize = AtomicUsize::new(0);
+
+/// Downloads data files to a local cache directory
+///
+/// Each file is downloaded at most once per container: later calls (and later runs of
+/// `load` in the same container) return the cached copy. Files are cached as stored (without
+/// decompressing them), and are written to a temporary path that is only moved into the cache
+/// once the download completes (and its size matches the size reported by the data API, when it
+/// reports one), so an interrupted download is never mistaken for a cached file.
+///
+/// ```rust,no_run
+/// # use algorithmia::Algorithmia;
+/// use algorithmia::handler::Assets;
+///
+/// let client = Algorithmia::new()?;
+/// let paths = Assets::new(client)
+///     .with_dir("/tmp/models")
+///     .fetch(&["data://.my/models/weights.bin", "data://.my/models/vocab.txt"])?;
+/// # Ok::<(), Box<std::error::Error>>(())
+/// ```
+pub struct Assets {
+    client: Algorithmia,
+    dir: PathBuf,
+}
+
+impl Assets {
+    /// Instantiate an asset cache that downloads files with `client`
+    ///
+    /// The cache directory defaults to `algorithmia-assets` in the system temp directory,
+    /// and can be overridden with the `ALGORITHMIA_ASSET_CACHE` environment variable.
+    pub fn new(client: Algorithmia) -> Assets {
+        let dir = env::var_os(CACHE_DIR_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| env::temp_dir().join("algorithmia-assets"));
+        Assets {
+            client: client,
+            dir: dir,
+        }
+    }
+
+    /// Builder method to set the cache directory
+    pub fn with_dir<P: Into<PathBuf>>(mut self, dir: P) -> Assets {
+        self.dir = dir.into();
+        self
+    }
+
+    /// Download each data URI concurrently, returning the local paths in the same order
+    ///
+    /// Files that are already cached are not downloaded again.
+    pub fn fetch<I, S>(&self, uris: I) -> Result<Vec<PathBuf>, Error>
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<str>,
+    {
+        let files: Vec<DataFile> = uris
+            .into_iter()
+            .map(|uri| self.client.file(uri.as_ref()))
+            .collect();
+        fs::create_dir_all(&self.dir)
+            .with_context(|| format!("failed to create asset cache '{}'", self.dir.display()))?;
+
+        crossbeam_utils::thread::scope(|scope| {
+            let downloads: Vec<_> = files
+                .iter()
+                .map(|file| scope.spawn(move |_| self.fetch_file(file)))
+                .collect();
+            downloads
+                .into_iter()
+                .zip(&files)
+                .map(|(download, file)| {
+                    download.join().unwrap_or_else(|_| {
+                        Err(err_msg(format!(
+                            "download of '{}' panicked",
+                            file.to_data_uri()
+                        )))
+                    })
+                })
+                .collect()
+        })
+        .unwrap_or_else(|_| Err(err_msg("asset download panicked")))
+    }
+
+    fn fetch_file(&self, file: &DataFile) -> Result<PathBuf, Error> {
+        let path = self.cache_path(file);
+        if path.is_file() {
+            return Ok(path);
+        }
+        download(file, &path)
+            .with_context(|| format!("failed to download asset '{}'", file.to_data_uri()))?;
+        Ok(path)
+    }
+
+    // Keyed by the full data URI, keeping the file name so that its extension is preserved
+    fn cache_path(&self, file: &DataFile) -> PathBuf {
+        let uri = file.to_data_uri();
+        let name = file.basename().unwrap_or_else(|| "asset".into());
+        self.dir
+            .join(format!("{:016x}", fnv1a(uri.as_bytes())))
+            .join(name)
+    }
+}
+
+/// Download data files to the default cache directory with a client configured from the environment
+///
+/// See [`Assets`](struct.Assets.html) for how files are cached.
+///
+/// ```rust,no_run
+/// use algorithmia::handler::{self, assets};
+///
+/// fn apply(text: String, model: &mut Vec<u8>) -> Result<String, String> {
+///     Ok(format!("{} bytes of model for {}", model.len(), text))
+/// }
+///
+/// fn main() -> Result<(), Box<std::error::Error>> {
+///     handler::load_and_run(
+///         || {
+///             let paths = assets(&["data://.my/models/weights.bin"])?;
+///             Ok::<_, Box<std::error::Error>>(std::fs::read(&paths[0])?)
+///         },
+///         apply,
+///     )
+/// }
+/// ```
+pub fn assets<I, S>(uris: I) -> Result<Vec<PathBuf>, Error>
+where
+    I: IntoIterator<Item = S>,
+    S: AsRef<str>,
+{
+    Assets::new(Algorithmia::new()?).fetch(uris)
+}
+
+// 64-bit FNV-1a, which (unlike `DefaultHasher`) is the same in every build,
+// so that the cache paths of a container's files don't change when the algorithm is rebuilt
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+// Download to a temporary file next to `path`, moving it into place once complete
+fn download(file: &DataFile, path: &Path) -> Result<(), Error> {
+    // The size reported by the API is the size of the file as stored, so it isn't decompressed
+    let mut data = file.get_raw()?;
+    let dir = path.parent().expect("cache paths have a parent directory");
+    fs::create_dir_all(dir).context("failed to create cache directory")?;
+
+    // Unique even if the same file is downloaded by several threads at once
+    let download = DOWNLOADS.fetch_add(1, Ordering::SeqCst);
+    let partial = path.with_extension(format!("partial-{}-{}", process::id(), download));
+    let copied = File::create(&partial).and_then(|mut out| io::copy(&mut data, &mut out));
+    let result = match (copied, data.content_length) {
+        (Ok(size), Some(expected)) if size != expected => Err(err_msg(format!(
+            "downloaded {} bytes but expected {}",
+            size, expected
+        ))),
+        (Ok(_), _) => fs::rename(&partial, path).context("failed to move download into the cache"),
+        (Err(err), _) => Err(err).context("failed to write download"),
+    };
+    if result.is_err() {
+        let _ = fs::remove_file(&partial);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::data::tests::mock_server;
+
+    #[test]
+    fn test_cached_assets() {
+        let dir = env::temp_dir().join(format!("algorithmia-assets-{}", process::id()));
+        let client = Algorithmia::client_with_url("", "http://localhost:0").unwrap();
+        let assets = Assets::new(client).with_dir(&dir);
+
+        let uris = ["data://.my/models/a.bin", "data://.my/other/a.bin"];
+        let files: Vec<_> = uris.iter().map(|uri| assets.client.file(uri)).collect();
+        let paths: Vec<_> = files.iter().map(|file| assets.cache_path(file)).collect();
+        assert_ne!(paths[0], paths[1]);
+        assert_eq!(paths[0].file_name().unwrap(), "a.bin");
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+
+        // Cached files are returned without contacting the API
+        for path in &paths {
+            fs::create_dir_all(path.parent().unwrap()).unwrap();
+            fs::write(path, b"model").unwrap();
+        }
+        assert_eq!(assets.fetch(&uris).unwrap(), paths);
+
+        fs::remove_file(&paths[1]).unwrap();
+        assert!(assets.fetch(&uris).is_err());
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_duplicate_assets() {
+        let dir = env::temp_dir().join(format!("algorithmia-duplicate-assets-{}", process::id()));
+        let response =
+            "HTTP/1.1 200 OK\r\nConnection: close\r\nX-Data-Type: file\r\nContent-Length: 5\r\n\r\nmodel";
+        let assets = Assets::new(mock_server(vec![response, response])).with_dir(&dir);
+
+        // Both downloads of the same file succeed, even though they share a cache path
+        let uris = ["data://.my/models/a.bin", "data://.my/models/a.bin"];
+        let paths = assets.fetch(&uris).unwrap();
+        assert_eq!(paths[0], paths[1]);
+        assert_eq!(fs::read(&paths[0]).unwrap(), b"model");
+        assert_eq!(fs::read_dir(paths[0].parent().unwrap()).unwrap().count(), 1);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,119 @@
+//! Per-request context for handlers
+
+use super::{CancellationToken, Request};
+use crate::Algorithmia;
+use std::time::{Duration, Instant};
+
+/// Information about the request being processed
+///
+/// Passed to functions run with [`handler::run_with_context`](fn.run_with_context.html).
+/// It is filled from the request envelope and the algorithm's environment.
+///
+/// The `request_id`, `caller`, and `timeout` envelope fields are read on a best-effort basis:
+/// the platform isn't guaranteed to send them, so each of them may be `None` (e.g. when
+/// running locally), and algorithms shouldn't rely on them being present.
+pub struct Context {
+    request_id: Option<String>,
+    caller: Option<String>,
+    timeout: Option<Duration>,
+    token: CancellationToken,
+    client: Algorithmia,
+}
+
+// Request envelope fields other than the input data
+pub(super) struct RequestInfo {
+    pub(super) request_id: Option<String>,
+    caller: Option<String>,
+    timeout: Option<Duration>,
+    received: Instant,
+    pub(super) token: CancellationToken,
+}
+
+impl Context {
+    pub(super) fn new(info: RequestInfo, client: Algorithmia) -> Context {
+        Context {
+            request_id: info.request_id,
+            caller: info.caller,
+            timeout: info.timeout,
+            token: info.token,
+            client: client,
+        }
+    }
+
+    /// Identifier of the request, if sent by the platform
+    pub fn request_id(&self) -> Option<&str> {
+        self.request_id.as_ref().map(|id| &**id)
+    }
+
+    /// Username of the caller, if the request envelope includes it
+    pub fn caller(&self) -> Option<&str> {
+        self.caller.as_ref().map(|caller| &**caller)
+    }
+
+    /// Timeout requested by the caller (or the runner's default timeout), if any
+    ///
+    /// The request envelope may not include a timeout, so this is `None` unless
+    /// the runner has a [`default_timeout`](struct.Runner.html#method.default_timeout).
+    pub fn timeout(&self) -> Option<Duration> {
+        self.timeout
+    }
+
+    /// The time after which the caller will no longer wait for the result, if any
+    pub fn deadline(&self) -> Option<Instant> {
+        self.token.deadline()
+    }
+
+    /// Token that is cancelled once the deadline passes
+    ///
+    /// ```rust,no_run
+    /// # use algorithmia::handler::Context;
+    /// fn apply(steps: u32, ctx: &Context) -> Result<u32, Box<std::error::Error>> {
+    ///     let mut total = 0;
+    ///     for step in 0..steps {
+    ///         ctx.cancellation_token().check()?;
+    ///         total += step;
+    ///     }
+    ///     Ok(total)
+    /// }
+    /// ```
+    pub fn cancellation_token(&self) -> &CancellationToken {
+        &self.token
+    }
+
+    /// Client for calling other algorithms and accessing data as the algorithm
+    ///
+    /// The client is created once from the `ALGORITHMIA_API` and `ALGORITHMIA_API_KEY`
+    /// environment variables that the platform provides to the algorithm, so it is
+    /// authenticated with the algorithm's own API key rather than the caller's.
+    pub fn client(&self) -> &Algorithmia {
+        &self.client
+    }
+}
+
+impl RequestInfo {
+    // Set the deadline of requests that didn't specify a timeout
+    pub(super) fn default_timeout(&mut self, timeout: Duration) {
+        if self.timeout.is_none() {
+            self.timeout = Some(timeout);
+            self.token = CancellationToken::with_deadline(self.received + timeout);
+        }
+    }
+}
+
+impl<'a> From<&'a mut Request> for RequestInfo {
+    fn from(req: &'a mut Request) -> RequestInfo {
+        let received = Instant::now();
+        let timeout = req.timeout.map(Duration::from_secs);
+        let token = match timeout {
+            Some(timeout) => CancellationToken::with_deadline(received + timeout),
+            None => CancellationToken::new(),
+        };
+        RequestInfo {
+            request_id: req.request_id.clone(),
+            caller: req.caller.take(),
+            timeout: timeout,
+            received: received,
+            token: token,
+        }
+    }
+}
@@ -0,0 +1,205 @@
+//! Request deadlines and cooperative cancellation
+
+use crate::error::ApiError;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, LockResult, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Signals that the caller is no longer waiting for a request's result
+///
+/// A token is cancelled once the request's deadline passes (or when `cancel` is called).
+/// Long-running `apply` functions can poll it to stop early, e.g. with `token.check()?`.
+/// Tokens are cheap to clone, so they can be passed to worker threads.
+#[derive(Clone, Debug)]
+pub struct CancellationToken {
+    cancelled: Arc<AtomicBool>,
+    deadline: Option<Instant>,
+}
+
+impl CancellationToken {
+    /// Instantiate a token without a deadline that is only cancelled by `cancel`
+    pub fn new() -> CancellationToken {
+        CancellationToken {
+            cancelled: Arc::new(AtomicBool::new(false)),
+            deadline: None,
+        }
+    }
+
+    /// Instantiate a token that is cancelled once `deadline` passes
+    pub fn with_deadline(deadline: Instant) -> CancellationToken {
+        CancellationToken {
+            deadline: Some(deadline),
+            ..CancellationToken::new()
+        }
+    }
+
+    /// Cancel this token and all of its clones
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::SeqCst);
+    }
+
+    /// Returns true if the token was cancelled or its deadline has passed
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::SeqCst) || self.remaining() == Some(Duration::from_secs(0))
+    }
+
+    /// The time after which the result will not be read, if any
+    pub fn deadline(&self) -> Option<Instant> {
+        self.deadline
+    }
+
+    /// Time remaining until the deadline, if any
+    pub fn remaining(&self) -> Option<Duration> {
+        let now = Instant::now();
+        self.deadline.map(|deadline| {
+            if deadline > now {
+                deadline - now
+            } else {
+                Duration::from_secs(0)
+            }
+        })
+    }
+
+    /// Returns a `TimeoutError` if the token is cancelled
+    ///
+    /// The error is reported to the caller as is when returned from `apply`.
+    pub fn check(&self) -> Result<(), ApiError> {
+        if self.is_cancelled() {
+            Err(timeout_error())
+        } else {
+            Ok(())
+        }
+    }
+}
+
+impl Default for CancellationToken {
+    fn default() -> CancellationToken {
+        CancellationToken::new()
+    }
+}
+
+pub(super) fn timeout_error() -> ApiError {
+    ApiError {
+        message: "request was cancelled because its deadline passed".into(),
+        error_type: Some("TimeoutError".into()),
+        stacktrace: None,
+        code: None,
+        details: None,
+        _dummy: (),
+    }
+}
+
+/// Responds with a timeout failure when the current request passes its deadline
+pub(super) struct Watchdog {
+    state: Mutex<WatchState>,
+    wake: Condvar,
+}
+
+struct WatchState {
+    pending: Option<Pending>,
+    stopped: bool,
+}
+
+struct Pending {
+    token: CancellationToken,
+    deadline: Instant,
+    request_id: Option<String>,
+    timed_out: bool,
+}
+
+enum Action {
+    Wait(Option<Duration>),
+    TimeOut(Option<String>),
+    Stop,
+}
+
+impl Watchdog {
+    pub(super) fn new() -> Watchdog {
+        Watchdog {
+            state: Mutex::new(WatchState {
+                pending: None,
+                stopped: false,
+            }),
+            wake: Condvar::new(),
+        }
+    }
+
+    /// Start watching a request's deadline
+    pub(super) fn watch(&self, token: &CancellationToken, request_id: Option<&str>) {
+        if let Some(deadline) = token.deadline() {
+            self.lock().pending = Some(Pending {
+                token: token.clone(),
+                deadline: deadline,
+                request_id: request_id.map(String::from),
+                timed_out: false,
+            });
+            self.wake.notify_one();
+        }
+    }
+
+    /// Stop watching the current request, returning false if it already timed out
+    pub(super) fn finish(&self) -> bool {
+        let pending = self.lock().pending.take();
+        !pending.map_or(false, |pending| pending.timed_out)
+    }
+
+    /// Stop `run` once any timeout in progress is written
+    pub(super) fn stop(&self) {
+        self.lock().stopped = true;
+        self.wake.notify_one();
+    }
+
+    /// Call `respond` with the request id of each request that passes its deadline until stopped
+    pub(super) fn run<F, E>(&self, mut respond: F) -> Result<(), E>
+    where
+        F: FnMut(Option<String>) -> Result<(), E>,
+    {
+        let mut state = self.lock();
+        loop {
+            let stopped = state.stopped;
+            let action = match &mut state.pending {
+                _ if stopped => Action::Stop,
+                Some(pending) if !pending.timed_out => {
+                    let now = Instant::now();
+                    if now < pending.deadline {
+                        Action::Wait(Some(pending.deadline - now))
+                    } else {
+                        // Decided while locked, so `finish` won't also write a response
+                        pending.timed_out = true;
+                        pending.token.cancel();
+                        Action::TimeOut(pending.request_id.clone())
+                    }
+                }
+                _ => Action::Wait(None),
+            };
+            state = match action {
+                Action::Stop => return Ok(()),
+                Action::Wait(Some(timeout)) => recover(self.wake.wait_timeout(state, timeout)).0,
+                Action::Wait(None) => recover(self.wake.wait(state)),
+                Action::TimeOut(request_id) => {
+                    drop(state);
+                    respond(request_id)?;
+                    self.lock()
+                }
+            };
+        }
+    }
+
+    fn lock(&self) -> MutexGuard<WatchState> {
+        recover(self.state.lock())
+    }
+}
+
+/// Stops a watchdog when dropped
+pub(super) struct StopGuard<'w>(pub(super) &'w Watchdog);
+
+impl<'w> Drop for StopGuard<'w> {
+    fn drop(&mut self) {
+        self.0.stop();
+    }
+}
+
+// The watched state is consistent even if a panic poisoned the lock
+fn recover<T>(result: LockResult<T>) -> T {
+    result.unwrap_or_else(|err| err.into_inner())
+}
@@ -0,0 +1,97 @@
+//! Runners called by the `main` generated by `#[algorithmia::entrypoint]` [feature = "macros"]
+//!
+//! Unlike `handler::run` and `handler::load_and_run`, they bound each type of `apply`
+//! by a single trait, so an unsupported type is reported as an unsatisfied bound on
+//! that trait (at the type in the signature) instead of deep in the runner.
+
+use super::{AlgoOutput, Context};
+use crate::algo::TryFrom;
+use crate::prelude::AlgoIo;
+use std::error::Error;
+use std::process;
+
+/// Input types of `apply`: `String`, `ByteVec`, `AlgoIo`, `serde_json::Value`, or `Deserialize` types
+pub trait Input: TryFrom<AlgoIo, Error = <Self as Input>::DecodeError> {
+    type DecodeError: Into<Box<dyn Error>>;
+}
+
+impl<T> Input for T
+where
+    T: TryFrom<AlgoIo>,
+    T::Error: Into<Box<dyn Error>>,
+{
+    type DecodeError = T::Error;
+}
+
+/// Output types of `apply`: `String`, `ByteVec`, `AlgoIo`, `AlgoOutput`, `serde_json::Value`, or `Serialize` types
+///
+/// Only `ByteVec` and `AlgoIo` produce binary output: `Vec<u8>` serializes as a JSON array of numbers
+pub trait Output: Into<AlgoOutput> {}
+
+impl<T: Into<AlgoOutput>> Output for T {}
+
+/// Error types of `apply` and `load`: `String`, `&str`, `Box<dyn Error>`, or `Error` types
+pub trait ApplyError: Into<Box<dyn Error>> {}
+
+impl<T: Into<Box<dyn Error>>> ApplyError for T {}
+
+// Type parameters for the types of `apply` come first, so that the macro can pass
+// each type with its span: an unsupported type is then reported where it is written.
+
+pub fn run<IN, OUT, E, F>(apply: F)
+where
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: Input,
+    OUT: Output,
+    E: ApplyError,
+{
+    super::run(apply)
+}
+
+pub fn run_with_context<IN, OUT, E, F>(apply: F)
+where
+    F: FnMut(IN, &Context) -> Result<OUT, E>,
+    IN: Input,
+    OUT: Output,
+    E: ApplyError,
+{
+    super::run_with_context(apply)
+}
+
+pub fn load_and_run<IN, OUT, E, STATE, E3, LOAD, F>(load: LOAD, apply: F)
+where
+    LOAD: FnOnce() -> Result<STATE, E3>,
+    F: FnMut(IN, &mut STATE) -> Result<OUT, E>,
+    IN: Input,
+    OUT: Output,
+    E: ApplyError,
+    E3: ApplyError,
+{
+    if let Err(err) = super::load_and_run(load, apply) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+// Expanded by `#[entrypoint(schema)]`: without the `schema` feature, this reports the
+// missing feature instead of a missing `handler::schema`
+#[cfg(feature = "schema")]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __entrypoint_schema {
+    ($input:ty, $output:ty) => {
+        if ::std::env::args().skip(1).any(|arg| arg == "--schema") {
+            println!("{}", $crate::handler::schema::<$input, $output>());
+            return;
+        }
+    };
+}
+
+#[cfg(not(feature = "schema"))]
+#[doc(hidden)]
+#[macro_export]
+macro_rules! __entrypoint_schema {
+    ($input:ty, $output:ty) => {
+        compile_error!("#[entrypoint(schema)] requires the `schema` feature of `algorithmia`");
+    };
+}
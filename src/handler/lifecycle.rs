@@ -0,0 +1,493 @@
+//! Handler lifecycle: loading, warmup, and shutdown around the request loop
+
+use super::AlgoOutput;
+use crate::algo::TryFrom;
+#[cfg(unix)]
+use crate::error::ResultExt;
+use crate::error::{err_msg, Error as AlgoError};
+use crate::prelude::AlgoIo;
+use lazy_static::lazy_static;
+#[cfg(unix)]
+use signal_hook::{consts::SIGTERM, SigId};
+use std::cmp;
+use std::error::Error;
+use std::io::{self, BufRead, Read};
+use std::marker::PhantomData;
+use std::mem;
+#[cfg(unix)]
+use std::os::unix::net::UnixStream;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{self, Receiver, SyncSender};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::thread;
+
+/// An algorithm with hooks for each stage of its lifetime
+///
+/// The runner calls `load` and then `warmup` before signaling that the algorithm is ready
+/// to accept requests. It then calls `apply` for each request until stdin reaches EOF or
+/// the process receives `SIGTERM`, and finally calls `shutdown`. On `SIGTERM`, the request
+/// in progress is completed before shutting down, and no further requests are read.
+/// Stdin is read by one runner at a time, and input that a runner stops before reading
+/// is left for the next runner that reads stdin.
+///
+/// If `load` or `warmup` fails, the algorithm fails to start, so `warmup` also serves as
+/// a health check (e.g. running a sample input through a model). `shutdown` is called
+/// even if writing a response fails or `apply` panics (when panics aren't caught).
+///
+/// `run_stream`, `run_concurrent`, and `run_with_context` take functions rather than a `Handler`,
+/// so they have no lifecycle hooks: load any state before calling them (or use `load_and_run_concurrent`).
+///
+/// ```rust,no_run
+/// use algorithmia::handler::{self, Handler};
+/// use std::error::Error;
+///
+/// #[derive(Default)]
+/// struct Classifier { model: Vec<u8>, requests: u64 }
+///
+/// impl Handler for Classifier {
+///     type Input = String;
+///     type Output = String;
+///     type Error = String;
+///
+///     fn load(&mut self) -> Result<(), Box<Error>> {
+///         self.model = std::fs::read("model.bin")?;
+///         Ok(())
+///     }
+///
+///     fn warmup(&mut self) -> Result<(), Box<Error>> {
+///         self.apply("warmup".into())?;
+///         Ok(())
+///     }
+///
+///     fn apply(&mut self, input: String) -> Result<String, String> {
+///         self.requests += 1;
+///         Ok(format!("{} bytes of model for {}", self.model.len(), input))
+///     }
+///
+///     fn shutdown(&mut self) -> Result<(), Box<Error>> {
+///         eprintln!("served {} requests", self.requests);
+///         Ok(())
+///     }
+/// }
+///
+/// fn main() {
+///     handler::run_handler(Classifier::default())
+/// }
+/// ```
+pub trait Handler {
+    /// Type that each request is converted into (see [`handler::run`](fn.run.html) for valid input types)
+    type Input: TryFrom<AlgoIo>;
+    /// Type returned for a successful request
+    type Output: Into<AlgoOutput>;
+    /// Type returned for a failed request
+    type Error: Into<Box<dyn Error>>;
+
+    /// Load any state needed to process requests (e.g. a model)
+    fn load(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Prepare to process requests once loaded, e.g. by warming caches
+    fn warmup(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+
+    /// Process a single request
+    fn apply(&mut self, input: Self::Input) -> Result<Self::Output, Self::Error>;
+
+    /// Release resources (e.g. flush metrics or close connection pools) after the last request
+    fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+        Ok(())
+    }
+}
+
+// Shuts down a handler when dropped, so that it is shut down even if the request loop fails or panics
+pub(super) struct ShutdownGuard<'h, H: Handler> {
+    pub(super) handler: &'h mut H,
+    done: bool,
+}
+
+impl<'h, H: Handler> ShutdownGuard<'h, H> {
+    pub(super) fn new(handler: &'h mut H) -> ShutdownGuard<'h, H> {
+        ShutdownGuard {
+            handler: handler,
+            done: false,
+        }
+    }
+
+    pub(super) fn shutdown(mut self) -> Result<(), Box<dyn Error>> {
+        self.done = true;
+        self.handler.shutdown()
+    }
+}
+
+impl<'h, H: Handler> Drop for ShutdownGuard<'h, H> {
+    fn drop(&mut self) {
+        if !self.done {
+            let _ = self.handler.shutdown();
+        }
+    }
+}
+
+// Adapts an `apply` function to a `Handler`
+pub(super) struct ApplyFn<F, IN> {
+    apply: F,
+    _input: PhantomData<fn(IN)>,
+}
+
+impl<F, IN> ApplyFn<F, IN> {
+    pub(super) fn new(apply: F) -> ApplyFn<F, IN> {
+        ApplyFn {
+            apply: apply,
+            _input: PhantomData,
+        }
+    }
+}
+
+impl<F, IN, OUT, E> Handler for ApplyFn<F, IN>
+where
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+{
+    type Input = IN;
+    type Output = OUT;
+    type Error = E;
+
+    fn apply(&mut self, input: IN) -> Result<OUT, E> {
+        (self.apply)(input)
+    }
+}
+
+// Adapts `load` and `apply` functions to a `Handler`
+pub(super) struct LoadAndApply<LOAD, F, STATE, IN> {
+    load: Option<LOAD>,
+    state: Option<STATE>,
+    apply: F,
+    _input: PhantomData<fn(IN)>,
+}
+
+impl<LOAD, F, STATE, IN> LoadAndApply<LOAD, F, STATE, IN> {
+    pub(super) fn new(load: LOAD, apply: F) -> LoadAndApply<LOAD, F, STATE, IN> {
+        LoadAndApply {
+            load: Some(load),
+            state: None,
+            apply: apply,
+            _input: PhantomData,
+        }
+    }
+}
+
+impl<LOAD, F, STATE, IN, OUT, E, E3> Handler for LoadAndApply<LOAD, F, STATE, IN>
+where
+    LOAD: FnOnce() -> Result<STATE, E3>,
+    F: FnMut(IN, &mut STATE) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E3: Into<Box<dyn Error>>,
+{
+    type Input = IN;
+    type Output = OUT;
+    type Error = E;
+
+    fn load(&mut self) -> Result<(), Box<dyn Error>> {
+        if let Some(load) = self.load.take() {
+            self.state = Some(load().map_err(|err| err.into())?);
+        }
+        Ok(())
+    }
+
+    fn apply(&mut self, input: IN) -> Result<OUT, E> {
+        let state = self
+            .state
+            .as_mut()
+            .expect("handler state is loaded before processing requests");
+        (self.apply)(input, state)
+    }
+}
+
+/// Source of newline-delimited requests
+pub(super) enum Input<'a> {
+    // Read on a background thread so that SIGTERM can stop a blocked read
+    Stdin,
+    Reader(Box<dyn BufRead + 'a>),
+}
+
+/// Iterator over request lines that ends at EOF (or on SIGTERM when reading stdin)
+pub(super) type Requests<'b> = Box<dyn Iterator<Item = io::Result<String>> + 'b>;
+
+/// A chunk read from stdin, or a wakeup after `SIGTERM`
+enum Event {
+    Read(io::Result<Vec<u8>>),
+    Stop,
+}
+
+// Stdin is read by a single thread for the whole process, so that input read after
+// one runner stops (e.g. on SIGTERM) is left for a later runner instead of being lost
+struct SharedStdin {
+    events: Receiver<Event>,
+    // Cloned for each runner's SIGTERM listener
+    sender: SyncSender<Event>,
+    // The unread part of a chunk that was received after its runner stopped
+    pending: Vec<u8>,
+    eof: bool,
+    // Registered once for the process: set while no runner handles `SIGTERM`,
+    // so that `SIGTERM` terminates the process again
+    #[cfg(unix)]
+    restore_default: Option<Arc<AtomicBool>>,
+}
+
+lazy_static! {
+    // Taken by the runner that is reading stdin
+    static ref STDIN: Mutex<Option<SharedStdin>> = Mutex::new(Some(SharedStdin::new()));
+}
+
+impl SharedStdin {
+    fn new() -> SharedStdin {
+        let (sender, events) = mpsc::sync_channel(0);
+        let stdin_tx = sender.clone();
+        // Detached, since it may be blocked reading stdin after every runner returns
+        thread::spawn(move || {
+            let stdin = io::stdin();
+            let mut stdin = stdin.lock();
+            loop {
+                let chunk = match stdin.fill_buf() {
+                    Ok(buf) => {
+                        let end = buf
+                            .iter()
+                            .position(|&b| b == b'\n')
+                            .map_or(buf.len(), |n| n + 1);
+                        buf[..end].to_vec()
+                    }
+                    Err(ref err) if err.kind() == io::ErrorKind::Interrupted => continue,
+                    Err(err) => {
+                        let _ = stdin_tx.send(Event::Read(Err(err)));
+                        Vec::new()
+                    }
+                };
+                stdin.consume(chunk.len());
+                let eof = chunk.is_empty();
+                if stdin_tx.send(Event::Read(Ok(chunk))).is_err() || eof {
+                    return;
+                }
+            }
+        });
+        SharedStdin {
+            events: events,
+            sender: sender,
+            pending: Vec::new(),
+            eof: false,
+            #[cfg(unix)]
+            restore_default: None,
+        }
+    }
+}
+
+/// Stdin, read in chunks that each end at most at a newline, that ends at EOF or on SIGTERM
+///
+/// On SIGTERM, the rest of the current line is still read so that the request in progress
+/// can complete. Only one `StdinStream` can exist at a time: dropping it leaves any input
+/// it hasn't read for the next one.
+pub(super) struct StdinStream {
+    // Returned for later runners when dropped
+    stdin: Option<SharedStdin>,
+    // Set on SIGTERM, and checked before each chunk so that a stop takes priority over input
+    stopped: Arc<AtomicBool>,
+    // Stops listening for SIGTERM when dropped
+    _signals: Option<SignalHandle>,
+    chunk: Vec<u8>,
+    pos: usize,
+    stopping: bool,
+    eof: bool,
+}
+
+/// Stops reading requests on `SIGTERM` until it is dropped
+#[cfg(unix)]
+pub(super) struct SignalHandle {
+    id: SigId,
+    restore_default: Arc<AtomicBool>,
+}
+#[cfg(not(unix))]
+pub(super) type SignalHandle = ();
+
+#[cfg(unix)]
+impl Drop for SignalHandle {
+    fn drop(&mut self) {
+        // Closes the pipe, which ends the thread waiting for the signal
+        signal_hook::low_level::unregister(self.id);
+        self.restore_default.store(true, Ordering::SeqCst);
+    }
+}
+
+impl<'a> Input<'a> {
+    pub(super) fn requests<'b>(&'b mut self) -> Result<Requests<'b>, AlgoError> {
+        match self {
+            Input::Reader(reader) => Ok(Box::new(reader.lines())),
+            Input::Stdin => Ok(Box::new(StdinStream::new()?.lines())),
+        }
+    }
+}
+
+impl StdinStream {
+    pub(super) fn new() -> Result<StdinStream, AlgoError> {
+        let stdin = STDIN
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .take()
+            .ok_or_else(|| err_msg("stdin is already being read by another runner"))?;
+        let mut stream = StdinStream {
+            stdin: Some(stdin),
+            stopped: Arc::new(AtomicBool::new(false)),
+            _signals: None,
+            chunk: Vec::new(),
+            pos: 0,
+            stopping: false,
+            eof: false,
+        };
+        let stopped = stream.stopped.clone();
+        stream._signals = stop_on_sigterm(stream.shared(), stopped)?;
+        Ok(stream)
+    }
+
+    fn shared(&mut self) -> &mut SharedStdin {
+        self.stdin
+            .as_mut()
+            .expect("stdin is returned only when the stream is dropped")
+    }
+
+    // Whether the last chunk was read to the end of its line (or no chunk was read)
+    fn at_line_end(&self) -> bool {
+        self.pos >= self.chunk.len() && self.chunk.last().map_or(true, |&b| b == b'\n')
+    }
+}
+
+impl Read for StdinStream {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        let n = {
+            let mut buf = self.fill_buf()?;
+            buf.read(out)?
+        };
+        self.consume(n);
+        Ok(n)
+    }
+}
+
+impl BufRead for StdinStream {
+    fn fill_buf(&mut self) -> io::Result<&[u8]> {
+        while self.pos >= self.chunk.len() && !self.eof {
+            if self.stopped.load(Ordering::SeqCst) {
+                self.stopping = true;
+            }
+            let at_line_end = self.at_line_end();
+            if self.stopping && at_line_end {
+                self.eof = true;
+                break;
+            }
+            let stopped = self.stopped.clone();
+            let stdin = self.shared();
+            if !stdin.pending.is_empty() {
+                let chunk = mem::replace(&mut stdin.pending, Vec::new());
+                self.chunk = chunk;
+                self.pos = 0;
+                continue;
+            }
+            if stdin.eof {
+                self.eof = true;
+                break;
+            }
+            match stdin.events.recv() {
+                // Between requests, a SIGTERM received while waiting wins over the next line
+                Ok(Event::Read(Ok(ref chunk)))
+                    if at_line_end && !chunk.is_empty() && stopped.load(Ordering::SeqCst) =>
+                {
+                    stdin.pending.extend_from_slice(chunk);
+                }
+                Ok(Event::Read(Ok(chunk))) => {
+                    stdin.eof = chunk.is_empty();
+                    self.eof = chunk.is_empty();
+                    self.chunk = chunk;
+                    self.pos = 0;
+                }
+                Ok(Event::Read(Err(err))) => return Err(err),
+                // Possibly left over from an earlier runner, so only `stopped` is trusted
+                Ok(Event::Stop) => (),
+                Err(_) => self.eof = true,
+            }
+        }
+        if self.eof {
+            return Ok(&[]);
+        }
+        Ok(&self.chunk[self.pos..])
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.pos = cmp::min(self.pos + n, self.chunk.len());
+    }
+}
+
+impl Drop for StdinStream {
+    fn drop(&mut self) {
+        self._signals.take();
+        if let Some(mut stdin) = self.stdin.take() {
+            if self.pos < self.chunk.len() {
+                let mut rest = self.chunk.split_off(self.pos);
+                rest.extend_from_slice(&stdin.pending);
+                stdin.pending = rest;
+            }
+            *STDIN.lock().unwrap_or_else(PoisonError::into_inner) = Some(stdin);
+        }
+    }
+}
+
+// Signal-hook doesn't restore the default action of a signal when its last action is unregistered,
+// so a conditional default action is registered once to terminate the process while no runner listens
+#[cfg(unix)]
+fn stop_on_sigterm(
+    stdin: &mut SharedStdin,
+    stopped: Arc<AtomicBool>,
+) -> Result<Option<SignalHandle>, AlgoError> {
+    let restore_default = match &stdin.restore_default {
+        Some(restore_default) => restore_default.clone(),
+        None => {
+            let restore_default = Arc::new(AtomicBool::new(false));
+            signal_hook::flag::register_conditional_default(SIGTERM, restore_default.clone())
+                .context("failed to register SIGTERM handler")?;
+            stdin.restore_default = Some(restore_default.clone());
+            restore_default
+        }
+    };
+    let (mut read, write) = UnixStream::pair().context("failed to register SIGTERM handler")?;
+    let id = signal_hook::low_level::pipe::register(SIGTERM, write)
+        .context("failed to register SIGTERM handler")?;
+    restore_default.store(false, Ordering::SeqCst);
+    let event_tx = stdin.sender.clone();
+    thread::spawn(move || {
+        let mut buf = [0];
+        loop {
+            match read.read(&mut buf) {
+                Ok(1) => {
+                    stopped.store(true, Ordering::SeqCst);
+                    // Wakes up a runner waiting for stdin
+                    let _ = event_tx.send(Event::Stop);
+                    return;
+                }
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                _ => return,
+            }
+        }
+    });
+    Ok(Some(SignalHandle {
+        id: id,
+        restore_default: restore_default,
+    }))
+}
+
+#[cfg(not(unix))]
+fn stop_on_sigterm(
+    _: &mut SharedStdin,
+    _: Arc<AtomicBool>,
+) -> Result<Option<SignalHandle>, AlgoError> {
+    Ok(None)
+}
@@ -0,0 +1,58 @@
+//! Request-scoped logging [feature = "tracing"]
+
+use std::env;
+use std::error::Error;
+use std::io;
+use tracing::Span;
+use tracing_subscriber::filter::LevelFilter;
+
+/// Environment variable that sets the maximum log level (e.g. `debug`)
+const LOG_LEVEL_VAR: &'static str = "ALGORITHMIA_LOG";
+
+/// Install a subscriber that writes log lines to stderr [feature = "tracing"]
+///
+/// Stdout is reserved for the request protocol, so logs are written to stderr, where
+/// the platform collects them. While a request is processed, every line is tagged with
+/// its `request_id` (e.g. `INFO request{request_id="abc"}: my_algo: loaded 3 rows`).
+/// Records from the `log` crate are captured as well, and API calls made by the client
+/// are logged at the `debug` level with their method, URL, status, and duration.
+///
+/// The maximum level defaults to `info` and can be set with the `ALGORITHMIA_LOG`
+/// environment variable. This fails if a global subscriber is already installed.
+///
+/// ```rust,no_run
+/// # extern crate tracing_crate as tracing;
+/// use algorithmia::handler;
+///
+/// fn apply(name: String) -> Result<String, String> {
+///     tracing::info!("greeting {}", name);
+///     Ok(format!("Hello {}", name))
+/// }
+///
+/// fn main() {
+///     handler::init_logging().expect("failed to install logger");
+///     handler::run(apply)
+/// }
+/// ```
+pub fn init_logging() -> Result<(), Box<dyn Error + Send + Sync>> {
+    let level = match env::var(LOG_LEVEL_VAR) {
+        Ok(level) => level
+            .parse::<LevelFilter>()
+            .map_err(|err| format!("invalid {} '{}': {}", LOG_LEVEL_VAR, level, err))?,
+        Err(_) => LevelFilter::INFO,
+    };
+    tracing_subscriber::fmt()
+        .with_writer(io::stderr)
+        .with_ansi(false)
+        .with_max_level(level)
+        .try_init()
+}
+
+// Span that tags log lines emitted while processing a request with its id
+pub(super) fn request_span(request_id: Option<&str>) -> Span {
+    let span = tracing::info_span!("request", request_id = tracing::field::Empty);
+    if let Some(request_id) = request_id {
+        span.record("request_id", &request_id);
+    }
+    span
+}
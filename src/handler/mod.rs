@@ -0,0 +1,1918 @@
+//! Support for running Rust-based algorithms on the Algorithmia platform [feature = "handler"]
+
+use base64;
+use serde_json;
+
+use self::context::RequestInfo;
+use self::deadline::{StopGuard, Watchdog};
+use self::lifecycle::{ApplyFn, Input, LoadAndApply, ShutdownGuard, StdinStream};
+use self::stream::{RequestStream, Spool};
+use crate::algo::{AlgoData, TryFrom};
+use crate::error::{err_msg, ApiError, ResultExt};
+use crate::prelude::AlgoIo;
+use crate::Algorithmia;
+#[cfg(feature = "async")]
+use futures::executor::LocalPool;
+#[cfg(feature = "schema")]
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use std::cmp;
+use std::collections::BTreeMap;
+use std::env;
+use std::error::Error;
+use std::fs::OpenOptions;
+#[cfg(feature = "async")]
+use std::future::Future;
+use std::io::{self, BufRead, BufWriter, Read, Write};
+use std::mem;
+use std::path::PathBuf;
+use std::process;
+use std::sync::{mpsc, Arc, Mutex, MutexGuard};
+use std::time::Duration;
+
+mod assets;
+mod context;
+mod deadline;
+#[cfg(feature = "macros")]
+#[doc(hidden)]
+pub mod entrypoint;
+mod lifecycle;
+#[cfg(feature = "tracing")]
+mod logging;
+mod output;
+mod panic;
+#[cfg(feature = "serve")]
+mod serve;
+mod stream;
+pub mod testing;
+pub use self::assets::{assets, Assets};
+pub use self::context::Context;
+pub use self::deadline::CancellationToken;
+pub use self::lifecycle::Handler;
+#[cfg(feature = "tracing")]
+pub use self::logging::init_logging;
+pub use self::output::AlgoOutput;
+#[cfg(feature = "serve")]
+pub use self::serve::serve;
+pub use self::stream::BinaryInput;
+
+const ALGOOUT: &'static str = "/tmp/algoout";
+
+/// Environment variable that overrides the path of the algoout pipe
+const ALGOOUT_VAR: &'static str = "ALGOOUT";
+
+#[derive(Deserialize)]
+struct Request {
+    data: Value,
+    content_type: String,
+    #[serde(default)]
+    request_id: Option<String>,
+    // Optional envelope fields that the platform may not send
+    #[serde(default)]
+    caller: Option<String>,
+    // Timeout in seconds
+    #[serde(default)]
+    timeout: Option<u64>,
+}
+
+#[derive(Serialize)]
+struct AlgoSuccess {
+    result: Value,
+    metadata: RunnerMetadata,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct AlgoFailure {
+    error: RunnerError,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    request_id: Option<String>,
+}
+
+#[derive(Serialize)]
+struct RunnerMetadata {
+    content_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    duration: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    mime_type: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    alerts: Option<Vec<String>>,
+    #[serde(flatten)]
+    extra: Map<String, Value>,
+}
+
+/// Metadata fields that custom `AlgoOutput` metadata cannot override
+const RESERVED_METADATA: &[&str] = &["content_type", "duration", "mime_type", "alerts"];
+
+#[derive(Serialize)]
+struct RunnerError {
+    message: String,
+    error_type: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    stacktrace: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    details: Option<Value>,
+}
+
+impl AlgoSuccess {
+    fn new<S: Into<String>>(result: Value, content_type: S) -> AlgoSuccess {
+        AlgoSuccess {
+            result: result,
+            metadata: RunnerMetadata {
+                content_type: content_type.into(),
+                duration: None,
+                mime_type: None,
+                alerts: None,
+                extra: Map::new(),
+            },
+            request_id: None,
+        }
+    }
+}
+
+impl AlgoFailure {
+    fn new(err: &(dyn Error + 'static)) -> AlgoFailure {
+        // Pass structured errors through instead of flattening them, even if wrapped by other errors
+        let mut source = Some(err);
+        while let Some(err) = source {
+            if let Some(api_err) = err.downcast_ref::<ApiError>() {
+                return AlgoFailure::from(api_err);
+            }
+            let client_err = err.downcast_ref::<crate::error::Error>();
+            if let Some(api_err) = client_err.and_then(|err| err.api_error()) {
+                return AlgoFailure::from(api_err);
+            }
+            source = err.source();
+        }
+        AlgoFailure::with_type(err, "AlgorithmError")
+    }
+
+    fn system(err: &dyn Error) -> AlgoFailure {
+        AlgoFailure::with_type(err, "SystemError")
+    }
+
+    fn with_type(err: &dyn Error, error_type: &str) -> AlgoFailure {
+        AlgoFailure {
+            error: RunnerError {
+                message: error_cause_chain(err),
+                error_type: error_type.into(),
+                stacktrace: None,
+                code: None,
+                details: None,
+            },
+            request_id: None,
+        }
+    }
+}
+
+impl<'a> From<&'a ApiError> for AlgoFailure {
+    fn from(err: &'a ApiError) -> AlgoFailure {
+        AlgoFailure {
+            error: RunnerError {
+                message: err.message.clone(),
+                error_type: err
+                    .error_type
+                    .clone()
+                    .unwrap_or_else(|| "AlgorithmError".into()),
+                stacktrace: err.stacktrace.clone(),
+                code: err.code.clone(),
+                details: err.details.clone(),
+            },
+            request_id: None,
+        }
+    }
+}
+
+/// Configures the Algorithmia-compatible FaaS handler
+///
+/// This function is only used when authoring an algorithm to run on the Algorithmia platform.
+/// This function receives a handler function or closure that is used to process each individual request.
+/// It will block and only return once it reaches EOF of STDIN.
+///
+/// The handler function makes heavy use of generic conversion traits
+/// to provide flexibility in what functions it can accept.
+/// Essentially the handler is any function with an input argument that
+/// `AlgoIo` can be converted into, and an output that is either
+/// a type that can be convert into `AlgoIo` or any error type that
+/// can be coerced into a boxed `Error`.
+///
+/// ## Getting started
+///
+/// The simplest usage of this function is to just use a simple function that works entirely with `String`s:
+///
+/// ```rust
+/// use algorithmia::prelude::*;
+///
+/// fn apply(name: String) -> Result<String, String> {
+///     unimplemented!()
+/// }
+///
+/// fn main() {
+///     handler::run(apply)
+/// }
+/// ```
+///
+/// ## Automatic JSON serialization/deserialization
+///
+/// To use your own custom types as input and output, simply implement `Deserialize` and `Serialize` respectively.
+///
+/// ```rust
+/// #[derive(Deserialize)]
+/// struct Input { titles: Vec<String>, max: u32 }
+///
+/// #[derive(Serialize)]
+/// struct Output { titles: Vec<String> }
+///
+/// fn apply(input: Input) -> Result<Output, Box<Error>> {
+///     unimplemented!();
+/// }
+///
+/// fn main() {
+///     handler::run(apply)
+/// }
+/// ```
+///
+/// ## Input/Output types:
+/// **Valid input**
+/// - Any type that implements `serde::Deserialize` (e.g. `#[derive(Deserialize)]`.
+///   `String` accepts text input as is, as well as JSON strings
+/// - `algo::ByteVec`, `Vec<u8>`, or `bytes::Bytes` (`bytes` feature) if working with binary input
+/// - `algo::Either<L, R>` to accept text or JSON as `L` and binary as `R`
+/// - `algo::Image` to decode binary images (`image` feature)
+/// - `algo::Array` to decode nested JSON arrays into an `ndarray` array (`ndarray` feature)
+///
+/// **Valid output types (`Ok` variant of return value)**
+/// - Any type that implements `serde::Serialize` (e.g. `#[derive(Serialize)]`
+/// - `algo::ByteVec` if working with binary output. `Vec<u8>` and `bytes::Bytes` are serialized
+///   as a JSON array of numbers, so wrap them with `ByteVec::from` to return binary
+/// - `algo::Image` to return a PNG image (`image` feature)
+/// - `handler::AlgoOutput` wrapping any of the above to attach alerts, a MIME type, or custom metadata
+///
+/// **Valid error types (`Err` variant of return value)**
+/// Anything with an conversion to `Box<Error>`. This includes `String` and basically any type that implements the `Error` trait.
+/// Errors are reported with the `AlgorithmError` type, except for `error::ApiError`, which keeps its own
+/// `error_type`, `stacktrace`, `code`, and `details` (see also `error::AlgoErrorExt`).
+///
+/// A panic in `apply` is also reported as an `AlgorithmError`, including the panic message and backtrace,
+/// and later requests are still processed. Use [`Runner::catch_panics`](struct.Runner.html#method.catch_panics)
+/// to exit on panics instead.
+///
+/// ## Preloading and Maintaining State (Advanced Usage)
+///
+/// If your algorithm has a preload step that doesn't vary with user input (e.g. loading a model),
+/// you can perform that prior to calling `handler::run` and then passing in a reference to that stay via a capturing closure:
+///
+/// ```rust
+/// #[derive(Deserialize)]
+/// struct Input { titles: Vec<String>, max: u32 }
+///
+/// #[derive(Serialize)]
+/// struct Output { titles: Vec<String> }
+///
+/// struct App { model: Vec<u8> }
+///
+/// fn apply(input: Input, app: &App) -> Result<Output, String> {
+///     unimplemented!();
+/// }
+///
+/// fn main() {
+///     let app = App { model: load_model() };
+///     handler::run(|input| apply(input, &app) )
+/// }
+/// ```
+pub fn run<F, IN, OUT, E, E2>(apply: F)
+where
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<Error>>,
+    E2: Into<Box<Error>>,
+{
+    if let Err(err) = Runner::new().run(apply) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+pub fn load_and_run<F, LOAD, IN, OUT, STATE, E, E2, E3>(
+    load: LOAD,
+    apply: F,
+) -> Result<(), Box<Error>>
+where
+    F: FnMut(IN, &mut STATE) -> Result<OUT, E>,
+    LOAD: FnOnce() -> Result<STATE, E3>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<Error>>,
+    E2: Into<Box<Error>>,
+    E3: Into<Box<Error>>,
+{
+    Runner::new().load_and_run(load, apply)
+}
+
+/// Configures the FaaS handler for functions that also receive the request's [`Context`](struct.Context.html)
+///
+/// This behaves like [`handler::run`](fn.run.html), but `apply` also receives details of the request
+/// (e.g. the caller and requested timeout, when the platform sends them) and a client for calling
+/// other algorithms and accessing data, authenticated with the algorithm's own API key.
+///
+/// ```rust,no_run
+/// use algorithmia::prelude::*;
+/// use algorithmia::handler::Context;
+///
+/// fn apply(text: String, ctx: &Context) -> Result<AlgoIo, Box<std::error::Error>> {
+///     let summary = ctx.client().algo("nlp/Summarizer/0.1").pipe(text)?;
+///     Ok(summary.into())
+/// }
+///
+/// fn main() {
+///     handler::run_with_context(apply)
+/// }
+/// ```
+pub fn run_with_context<F, IN, OUT, E, E2>(apply: F)
+where
+    F: FnMut(IN, &Context) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    if let Err(err) = Runner::new().run_with_context(apply) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+/// Configures the FaaS handler with a [`Handler`](trait.Handler.html) that has lifecycle hooks
+///
+/// This behaves like [`handler::run`](fn.run.html), but also calls the handler's
+/// `load`, `warmup`, and `shutdown` hooks.
+pub fn run_handler<H>(handler: H)
+where
+    H: Handler,
+    <H::Input as TryFrom<AlgoIo>>::Error: Into<Box<dyn Error>>,
+{
+    if let Err(err) = Runner::new().run_handler(handler) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+/// Configures the FaaS handler for functions that stream binary input and output
+///
+/// Each request is passed to `apply` as a [`BinaryInput`](struct.BinaryInput.html) that reads the
+/// request's data directly from stdin, base64-decoding binary input as it is read. The returned reader
+/// is base64-encoded into a temporary file, which becomes the response once the output has been read.
+/// This avoids holding whole decoded and encoded copies of large binary payloads (e.g. images or audio)
+/// in memory. Text requests are read as UTF-8 bytes, and JSON requests are rejected with an `InputError`.
+/// The output is always returned with the `binary` content type, and an error reading it is
+/// reported as a failed request.
+///
+/// Any input that `apply` (or its output) doesn't read is skipped before the next request.
+/// On `SIGTERM`, a request that is partly read is still read to its end and processed
+/// before the handler stops.
+///
+/// ```rust,no_run
+/// use algorithmia::prelude::*;
+/// use algorithmia::handler::BinaryInput;
+/// use std::io::{self, Read};
+///
+/// fn apply(input: BinaryInput) -> Result<impl Read + '_, io::Error> {
+///     // e.g. transcode without buffering the whole payload
+///     Ok(input.take(1024))
+/// }
+///
+/// fn main() {
+///     handler::run_stream(apply)
+/// }
+/// ```
+pub fn run_stream<F, OUT, E>(apply: F)
+where
+    F: FnMut(BinaryInput<'static>) -> Result<OUT, E>,
+    OUT: Read,
+    E: Into<Box<dyn Error>>,
+{
+    if let Err(err) = Runner::new().run_stream(apply) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+/// Configures the FaaS handler to process up to `workers` requests concurrently
+///
+/// See [`Runner::run_concurrent`](struct.Runner.html#method.run_concurrent) for
+/// when this mode helps and how responses are ordered.
+pub fn run_concurrent<F, IN, OUT, E, E2>(workers: usize, apply: F)
+where
+    F: Fn(IN) -> Result<OUT, E> + Sync,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    if let Err(err) = Runner::new().run_concurrent(workers, apply) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+/// Like [`load_and_run`](fn.load_and_run.html), but shares the loaded state across `workers` concurrent requests
+pub fn load_and_run_concurrent<F, LOAD, IN, OUT, STATE, E, E2, E3>(
+    workers: usize,
+    load: LOAD,
+    apply: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: Fn(IN, &STATE) -> Result<OUT, E> + Sync,
+    LOAD: FnOnce() -> Result<STATE, E3>,
+    STATE: Send + Sync,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+    E3: Into<Box<dyn Error>>,
+{
+    Runner::new().load_and_run_concurrent(workers, load, apply)
+}
+
+/// Configures the Algorithmia-compatible FaaS handler for functions that return futures [feature = "async"]
+///
+/// This behaves like [`handler::run`](fn.run.html), except `apply` returns a
+/// `std::future::Future` (e.g. it is an `async fn`). Each future is driven to completion
+/// on the current thread, by an executor created once for the runner, before reading the
+/// next request. Unlike the rest of the handler, the `async` feature requires Rust 1.39 or later.
+///
+/// Only executor-agnostic futures are supported: the executor has no I/O reactor or timers,
+/// so futures that depend on a runtime (e.g. tokio-based database or HTTP clients, which
+/// panic with "no reactor running") fail. For those, create the runtime in `load` and call
+/// its `block_on` from a synchronous `apply` with [`load_and_run`](fn.load_and_run.html).
+///
+/// ```rust,no_run
+/// use algorithmia::prelude::*;
+///
+/// async fn apply(name: String) -> Result<String, String> {
+///     Ok(format!("Hello {}", name))
+/// }
+///
+/// fn main() {
+///     handler::run_async(apply)
+/// }
+/// ```
+#[cfg(feature = "async")]
+pub fn run_async<F, FUT, IN, OUT, E, E2>(apply: F)
+where
+    F: FnMut(IN) -> FUT,
+    FUT: Future<Output = Result<OUT, E>>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    if let Err(err) = Runner::new().run_async(apply) {
+        println!("{}\n", err);
+        process::exit(-1);
+    }
+}
+
+/// Like [`load_and_run`](fn.load_and_run.html), but `load` and `apply` return futures [feature = "async"]
+///
+/// The future returned by `apply` cannot borrow the state, so clone any
+/// handles it needs (e.g. a connection pool) into the future. As with
+/// [`run_async`](fn.run_async.html), only executor-agnostic futures are supported.
+#[cfg(feature = "async")]
+pub fn load_and_run_async<F, LOAD, LFUT, FUT, IN, OUT, STATE, E, E2, E3>(
+    load: LOAD,
+    apply: F,
+) -> Result<(), Box<dyn Error>>
+where
+    F: FnMut(IN, &mut STATE) -> FUT,
+    FUT: Future<Output = Result<OUT, E>>,
+    LOAD: FnOnce() -> LFUT,
+    LFUT: Future<Output = Result<STATE, E3>>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+    E3: Into<Box<dyn Error>>,
+{
+    Runner::new().load_and_run_async(load, apply)
+}
+
+/// Generates JSON Schemas for a handler's input and output types [feature = "schema"]
+///
+/// The schemas are returned as `{"input": <schema>, "output": <schema>}`,
+/// e.g. to include in an algorithm's documentation.
+///
+/// ```rust
+/// use algorithmia::handler;
+/// use schemars::JsonSchema;
+/// use serde::{Deserialize, Serialize};
+///
+/// #[derive(Deserialize, JsonSchema)]
+/// struct Input { titles: Vec<String>, max: u32 }
+///
+/// #[derive(Serialize, JsonSchema)]
+/// struct Output { titles: Vec<String> }
+///
+/// let schema = handler::schema::<Input, Output>();
+/// assert_eq!(schema["input"]["properties"]["max"]["type"], "integer");
+/// ```
+#[cfg(feature = "schema")]
+pub fn schema<IN: JsonSchema, OUT: JsonSchema>() -> Value {
+    serde_json::json!({
+        "input": schemars::schema_for!(IN),
+        "output": schemars::schema_for!(OUT),
+    })
+}
+
+/// Runs a handler against configurable request and response streams
+///
+/// `handler::run` uses a `Runner` that reads requests from stdin and writes
+/// responses to the algoout pipe: `/tmp/algoout`, or the path set in the `ALGOOUT`
+/// environment variable. Other streams can be configured to drive an algorithm
+/// with in-memory requests, e.g. from `cargo test`.
+///
+/// Unlike `handler::run`, failing to write a response returns an error instead of exiting.
+///
+/// ```rust
+/// use algorithmia::handler::Runner;
+/// use std::io::Cursor;
+///
+/// fn apply(name: String) -> Result<String, String> {
+///     Ok(format!("Hello {}", name))
+/// }
+///
+/// let requests = r#"{"content_type":"text","data":"world"}"#;
+/// let mut responses = Vec::new();
+/// Runner::new()
+///     .input(Cursor::new(requests))
+///     .output(&mut responses)
+///     .run(apply)?;
+///
+/// let responses = String::from_utf8(responses)?;
+/// assert!(responses.contains("Hello world"));
+/// # Ok::<(), Box<std::error::Error>>(())
+/// ```
+pub struct Runner<'a> {
+    input: Input<'a>,
+    output: Output<'a>,
+    catch_panics: bool,
+    default_timeout: Option<Duration>,
+    enforce_timeouts: bool,
+}
+
+enum Output<'a> {
+    // Reopened for every response since algoout is a named pipe
+    Pipe(PathBuf),
+    Writer(Box<dyn Write + Send + 'a>),
+}
+
+impl<'a> Runner<'a> {
+    /// Instantiate a `Runner` that reads from stdin and writes to the algoout pipe
+    pub fn new() -> Runner<'a> {
+        let algoout = env::var_os(ALGOOUT_VAR)
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(ALGOOUT));
+        Runner {
+            input: Input::Stdin,
+            output: Output::Pipe(algoout),
+            catch_panics: true,
+            default_timeout: None,
+            enforce_timeouts: false,
+        }
+    }
+
+    /// Builder method to read newline-delimited JSON requests from `input`
+    pub fn input<R: BufRead + 'a>(&mut self, input: R) -> &mut Runner<'a> {
+        self.input = Input::Reader(Box::new(input));
+        self
+    }
+
+    /// Builder method to write newline-delimited JSON responses to `output`
+    pub fn output<W: Write + Send + 'a>(&mut self, output: W) -> &mut Runner<'a> {
+        self.output = Output::Writer(Box::new(output));
+        self
+    }
+
+    /// Builder method to write responses to a named pipe, reopening it for each response
+    pub fn output_path<P: Into<PathBuf>>(&mut self, path: P) -> &mut Runner<'a> {
+        self.output = Output::Pipe(path.into());
+        self
+    }
+
+    /// Builder method to set whether a panic in `apply` is reported as a failed request
+    ///
+    /// By default, a panic is caught and reported as an `AlgorithmError` with the panic message
+    /// and backtrace, and the runner continues with the next request. Disable this if state
+    /// captured by `apply` may be left inconsistent by a panic, so that the process exits instead.
+    pub fn catch_panics(&mut self, catch_panics: bool) -> &mut Runner<'a> {
+        self.catch_panics = catch_panics;
+        self
+    }
+
+    /// Builder method to set the deadline of requests that don't specify a `timeout`
+    ///
+    /// The deadline is available from the [`Context`](struct.Context.html) passed by `run_with_context`,
+    /// and is enforced with `enforce_timeouts`. `run_concurrent`, `load_and_run_concurrent`,
+    /// and `run_stream` ignore it.
+    pub fn default_timeout(&mut self, timeout: Duration) -> &mut Runner<'a> {
+        self.default_timeout = Some(timeout);
+        self
+    }
+
+    /// Builder method to respond with a `TimeoutError` as soon as a request passes its deadline
+    ///
+    /// By default, the runner waits for `apply` to return even if the caller has stopped waiting.
+    /// When enabled, the runner writes a `TimeoutError` failure once the deadline passes and cancels
+    /// the request's [`CancellationToken`](struct.CancellationToken.html). `apply` keeps running
+    /// until it returns (e.g. after noticing the cancellation), and its result is then discarded.
+    /// The deadlines are watched on a background thread, which is started for the first request
+    /// that has a deadline.
+    ///
+    /// This applies to `run`, `load_and_run`, `run_handler`, and `run_with_context`:
+    /// `run_concurrent`, `load_and_run_concurrent`, and `run_stream` ignore it.
+    pub fn enforce_timeouts(&mut self, enforce_timeouts: bool) -> &mut Runner<'a> {
+        self.enforce_timeouts = enforce_timeouts;
+        self
+    }
+
+    /// Process each request with the `apply` function until the input reaches EOF
+    ///
+    /// See [`handler::run`](fn.run.html) for the functions that are accepted.
+    pub fn run<F, IN, OUT, E, E2>(&mut self, apply: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(IN) -> Result<OUT, E>,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+    {
+        self.run_handler(ApplyFn::new(apply))
+    }
+
+    /// Load and warm up `handler`, process each request until the input reaches EOF
+    /// (or the process receives `SIGTERM`), and then shut it down
+    ///
+    /// See [`Handler`](trait.Handler.html) for details of each stage.
+    pub fn run_handler<H>(&mut self, mut handler: H) -> Result<(), Box<dyn Error>>
+    where
+        H: Handler,
+        <H::Input as TryFrom<AlgoIo>>::Error: Into<Box<dyn Error>>,
+    {
+        handler.load()?;
+        handler.warmup()?;
+        let mut guard = ShutdownGuard::new(&mut handler);
+        let processed = self.process_requests(|input, _| {
+            call_apply(&mut |input| guard.handler.apply(input), input)
+        });
+        let shutdown = guard.shutdown();
+        processed.and(shutdown)
+    }
+
+    /// Process each request with the `apply` function and the request's [`Context`](struct.Context.html)
+    ///
+    /// See [`handler::run_with_context`](fn.run_with_context.html) for details.
+    pub fn run_with_context<F, IN, OUT, E, E2>(
+        &mut self,
+        mut apply: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(IN, &Context) -> Result<OUT, E>,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+    {
+        let client = Algorithmia::new()?;
+        self.process_requests(|input, info| {
+            let ctx = Context::new(info, client.clone());
+            call_apply(&mut |input| apply(input, &ctx), input)
+        })
+    }
+
+    // Signal that the algorithm is ready, and then process each request until EOF or SIGTERM
+    fn process_requests<G>(&mut self, mut apply: G) -> Result<(), Box<dyn Error>>
+    where
+        G: FnMut(AlgoIo, RequestInfo) -> Result<AlgoOutput, Box<dyn Error>>,
+    {
+        println!("PIPE_INIT_COMPLETE");
+        flush_std_pipes();
+
+        let Runner {
+            input,
+            output,
+            catch_panics,
+            default_timeout,
+            enforce_timeouts,
+        } = self;
+        let requests = input.requests()?;
+        if !*enforce_timeouts {
+            for line in requests {
+                let output_json = process_line(line, |input, mut info| {
+                    if let Some(timeout) = default_timeout {
+                        info.default_timeout(*timeout);
+                    }
+                    guard_apply(*catch_panics, || apply(input, info))
+                });
+                output.write_response(&output_json)?;
+            }
+            return Ok(());
+        }
+
+        // The watchdog writes a timeout response while `apply` is still running, so it shares the output
+        let output = Mutex::new(output);
+        let watchdog = Watchdog::new();
+        let written = crossbeam_utils::thread::scope(|scope| {
+            // Stops the watchdog even if `apply` panics, so that the scope can join it
+            let stop = StopGuard(&watchdog);
+            let mut timeouts = None;
+            let mut written = Ok(());
+            for line in requests {
+                let output_json = process_line(line, |input, mut info| {
+                    if let Some(timeout) = default_timeout {
+                        info.default_timeout(*timeout);
+                    }
+                    // Only started once a request has a deadline
+                    if timeouts.is_none() && info.token.deadline().is_some() {
+                        timeouts = Some(scope.spawn(|_| {
+                            watchdog.run(|request_id| {
+                                let err: Box<dyn Error> = Box::new(deadline::timeout_error());
+                                let output_json = serialize_output(Err(err), request_id);
+                                lock_output(&output).write_response(&output_json)
+                            })
+                        }));
+                    }
+                    watchdog.watch(&info.token, info.request_id.as_ref().map(|id| &**id));
+                    guard_apply(*catch_panics, || apply(input, info))
+                });
+                // A response was already written if the request timed out
+                if watchdog.finish() {
+                    written = lock_output(&output).write_response(&output_json);
+                    if written.is_err() {
+                        break;
+                    }
+                }
+            }
+
+            drop(stop);
+            let timed_out = match timeouts {
+                Some(timeouts) => timeouts
+                    .join()
+                    .unwrap_or_else(|_| Err(err_msg("timeout watchdog panicked"))),
+                None => Ok(()),
+            };
+            written.and(timed_out)
+        })
+        .unwrap_or_else(|_| Err(err_msg("timeout watchdog panicked")));
+        Ok(written?)
+    }
+
+    /// Process each request with an `apply` function that streams binary input and output
+    ///
+    /// See [`handler::run_stream`](fn.run_stream.html) for details.
+    pub fn run_stream<F, OUT, E>(&mut self, mut apply: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(BinaryInput<'a>) -> Result<OUT, E>,
+        OUT: Read,
+        E: Into<Box<dyn Error>>,
+    {
+        println!("PIPE_INIT_COMPLETE");
+        flush_std_pipes();
+
+        // The input is read directly by each `BinaryInput`, so it is moved out of the runner until EOF
+        let (reader, is_stdin): (Box<dyn BufRead + 'a>, _) =
+            match mem::replace(&mut self.input, Input::Stdin) {
+                Input::Reader(reader) => (reader, false),
+                Input::Stdin => (Box::new(StdinStream::new()?), true),
+            };
+        let requests = RequestStream::new(reader);
+        let result = self.process_stream(&requests, &mut apply);
+        match requests.into_reader() {
+            Some(reader) if !is_stdin => self.input = Input::Reader(reader),
+            _ => (),
+        }
+        result
+    }
+
+    fn process_stream<F, OUT, E>(
+        &mut self,
+        requests: &RequestStream<'a>,
+        apply: &mut F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(BinaryInput<'a>) -> Result<OUT, E>,
+        OUT: Read,
+        E: Into<Box<dyn Error>>,
+    {
+        let Runner {
+            output,
+            catch_panics,
+            ..
+        } = self;
+        while let Some(env) = requests.next_request() {
+            let mut env = match env {
+                Ok(env) => env,
+                Err(err) => {
+                    output.write_response(&serialize_output(Err(err), None))?;
+                    continue;
+                }
+            };
+            #[cfg(feature = "tracing")]
+            let _span = logging::request_span(env.request_id.as_ref().map(|id| &**id)).entered();
+            let result = requests
+                .input(&env)
+                .and_then(|input| {
+                    guard_apply(*catch_panics, || apply(input).map_err(|err| err.into()))
+                })
+                .and_then(stream::spool_output);
+            flush_std_pipes();
+            // The request ID and the rest of the request may follow its `data`
+            let result = requests.finish_request(&mut env).and(result);
+            let request_id = env.request_id.take();
+            match result {
+                Ok(spool) => output.write_stream(spool, request_id.as_ref().map(|id| &**id))?,
+                Err(err) => output.write_response(&serialize_output(Err(err), request_id))?,
+            }
+        }
+        Ok(())
+    }
+
+    /// Process up to `workers` requests concurrently with the `apply` function
+    ///
+    /// Responses are written in the same order as their requests, so a slow request delays
+    /// the responses to any requests after it. Concurrency only helps when the caller sends
+    /// more requests before reading earlier responses (e.g. a local test harness); a caller
+    /// that waits for each response gets the same behavior as `run`.
+    /// Stdout and stderr from concurrent requests may be interleaved.
+    ///
+    /// Requests have no deadlines (`default_timeout` and `enforce_timeouts` are ignored), and
+    /// there are no lifecycle hooks, although reading from stdin still stops on `SIGTERM`.
+    pub fn run_concurrent<F, IN, OUT, E, E2>(
+        &mut self,
+        workers: usize,
+        apply: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: Fn(IN) -> Result<OUT, E> + Sync,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+    {
+        println!("PIPE_INIT_COMPLETE");
+        flush_std_pipes();
+
+        let Runner {
+            input,
+            output,
+            catch_panics,
+            ..
+        } = self;
+        let catch_panics = *catch_panics;
+        let requests = input.requests()?;
+        let (line_tx, line_rx) = mpsc::sync_channel::<(usize, io::Result<String>)>(workers);
+        let (output_tx, output_rx) = mpsc::channel::<(usize, String)>();
+        // Workers own the receiver, so sending fails once they have all stopped
+        let line_rx = Arc::new(Mutex::new(line_rx));
+        let apply = &apply;
+
+        let written = crossbeam_utils::thread::scope(|scope| {
+            for _ in 0..cmp::max(workers, 1) {
+                let line_rx = line_rx.clone();
+                let output_tx = output_tx.clone();
+                scope.spawn(move |_| loop {
+                    let (index, line) = match line_rx.lock().map(|rx| rx.recv()) {
+                        Ok(Ok(request)) => request,
+                        _ => break,
+                    };
+                    let output_json = process_line(line, |input, _| {
+                        guard_apply(catch_panics, || call_apply(&mut &*apply, input))
+                    });
+                    if output_tx.send((index, output_json)).is_err() {
+                        break;
+                    }
+                });
+            }
+            drop((line_rx, output_tx));
+
+            // Hold back responses that complete early until the responses before them are written
+            let writer = scope.spawn(move |_| {
+                let mut pending = BTreeMap::new();
+                let mut next = 0;
+                for (index, output_json) in output_rx {
+                    pending.insert(index, output_json);
+                    while let Some(output_json) = pending.remove(&next) {
+                        output.write_response(&output_json)?;
+                        next += 1;
+                    }
+                }
+                Ok(())
+            });
+
+            for (index, line) in requests.enumerate() {
+                if line_tx.send((index, line)).is_err() {
+                    break;
+                }
+            }
+            drop(line_tx);
+
+            writer
+                .join()
+                .unwrap_or_else(|_| Err(err_msg("response writer panicked")))
+        })
+        .unwrap_or_else(|_| Err(err_msg("request worker panicked")));
+        Ok(written?)
+    }
+
+    /// Call `load` once, and then process each request with the `apply` function and loaded state
+    pub fn load_and_run<F, LOAD, IN, OUT, STATE, E, E2, E3>(
+        &mut self,
+        load: LOAD,
+        apply: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(IN, &mut STATE) -> Result<OUT, E>,
+        LOAD: FnOnce() -> Result<STATE, E3>,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+        E3: Into<Box<dyn Error>>,
+    {
+        self.run_handler(LoadAndApply::new(load, apply))
+    }
+
+    /// Call `load` once, and then process up to `workers` requests concurrently with shared state
+    ///
+    /// See [`run_concurrent`](#method.run_concurrent) for how responses are written.
+    pub fn load_and_run_concurrent<F, LOAD, IN, OUT, STATE, E, E2, E3>(
+        &mut self,
+        workers: usize,
+        load: LOAD,
+        apply: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: Fn(IN, &STATE) -> Result<OUT, E> + Sync,
+        LOAD: FnOnce() -> Result<STATE, E3>,
+        STATE: Send + Sync,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+        E3: Into<Box<dyn Error>>,
+    {
+        let state = load().map_err(|err| err.into())?;
+        self.run_concurrent(workers, |input| apply(input, &state))
+    }
+
+    /// Process each request by driving the future returned by `apply` to completion [feature = "async"]
+    ///
+    /// See [`handler::run_async`](fn.run_async.html) for details.
+    #[cfg(feature = "async")]
+    pub fn run_async<F, FUT, IN, OUT, E, E2>(&mut self, mut apply: F) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(IN) -> FUT,
+        FUT: Future<Output = Result<OUT, E>>,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+    {
+        let mut executor = LocalPool::new();
+        self.run(|input| executor.run_until(apply(input)))
+    }
+
+    /// Drive the future returned by `load` once, and then process each request with `apply` [feature = "async"]
+    ///
+    /// See [`handler::load_and_run_async`](fn.load_and_run_async.html) for details.
+    #[cfg(feature = "async")]
+    pub fn load_and_run_async<F, LOAD, LFUT, FUT, IN, OUT, STATE, E, E2, E3>(
+        &mut self,
+        load: LOAD,
+        mut apply: F,
+    ) -> Result<(), Box<dyn Error>>
+    where
+        F: FnMut(IN, &mut STATE) -> FUT,
+        FUT: Future<Output = Result<OUT, E>>,
+        LOAD: FnOnce() -> LFUT,
+        LFUT: Future<Output = Result<STATE, E3>>,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+        E3: Into<Box<dyn Error>>,
+    {
+        let mut executor = LocalPool::new();
+        let mut state = executor.run_until(load()).map_err(|err| err.into())?;
+        self.run(|input| executor.run_until(apply(input, &mut state)))
+    }
+}
+
+impl<'a> Default for Runner<'a> {
+    fn default() -> Runner<'a> {
+        Runner::new()
+    }
+}
+
+impl<'a> Output<'a> {
+    fn write_stream(
+        &mut self,
+        spool: Spool,
+        request_id: Option<&str>,
+    ) -> Result<(), crate::error::Error> {
+        match self {
+            Output::Pipe(path) => {
+                let f = OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .with_context(|| {
+                        format!("Cannot write to algoout pipe '{}'", path.display())
+                    })?;
+                stream::write_stream_response(BufWriter::new(f), spool, request_id)
+            }
+            Output::Writer(w) => stream::write_stream_response(w, spool, request_id),
+        }
+    }
+
+    fn write_response(&mut self, output_json: &str) -> Result<(), crate::error::Error> {
+        match self {
+            Output::Pipe(path) => {
+                let mut f = OpenOptions::new()
+                    .write(true)
+                    .open(&path)
+                    .with_context(|| {
+                        format!("Cannot write to algoout pipe '{}'", path.display())
+                    })?;
+                // A single write, so that the response line isn't interleaved with other writers
+                f.write_all(format!("{}\n", output_json).as_bytes())
+                    .context("Cannot write response")?;
+            }
+            Output::Writer(w) => {
+                writeln!(w, "{}", output_json).context("Cannot write response")?;
+                w.flush().context("Cannot write response")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+impl From<AlgoOutput> for AlgoSuccess {
+    fn from(output: AlgoOutput) -> AlgoSuccess {
+        let AlgoOutput {
+            result,
+            mime_type,
+            alerts,
+            mut metadata,
+        } = output;
+        let mut success = match result.data {
+            AlgoData::Text(text) => AlgoSuccess::new(Value::String(text), "text"),
+            AlgoData::Json(json_obj) => AlgoSuccess::new(json_obj, "json"),
+            AlgoData::Binary(bytes) => {
+                let result = base64::encode(&bytes);
+                AlgoSuccess::new(Value::String(result), "binary")
+            }
+        };
+        for key in RESERVED_METADATA {
+            metadata.remove(*key);
+        }
+        success.metadata.mime_type = mime_type.map(|mime| mime.to_string());
+        if !alerts.is_empty() {
+            success.metadata.alerts = Some(alerts);
+        }
+        success.metadata.extra = metadata;
+        success
+    }
+}
+
+fn call_apply<F, IN, OUT, E, E2>(apply: &mut F, input: AlgoIo) -> Result<AlgoOutput, Box<dyn Error>>
+where
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    match IN::try_from(input) {
+        Ok(algo_io) => match apply(algo_io) {
+            Ok(out) => Ok(out.into()),
+            Err(err) => Err(err.into()),
+        },
+        Err(err) => Err(input_error(err.into())),
+    }
+}
+
+// Report a failure to convert the request into the handler's input type as an `InputError`
+fn input_error(err: Box<dyn Error>) -> Box<dyn Error> {
+    let api_err = err
+        .downcast_ref::<crate::error::Error>()
+        .and_then(|err| err.api_error())
+        .cloned()
+        .unwrap_or_else(|| ApiError {
+            message: error_cause_chain(&*err),
+            error_type: Some("InputError".into()),
+            stacktrace: None,
+            code: None,
+            details: None,
+            _dummy: (),
+        });
+    Box::new(api_err)
+}
+
+// Call `apply`, optionally reporting a panic as a failed request
+fn guard_apply<T, G>(catch_panics: bool, apply: G) -> Result<T, Box<dyn Error>>
+where
+    G: FnOnce() -> Result<T, Box<dyn Error>>,
+{
+    if catch_panics {
+        panic::catch_panic(apply)
+    } else {
+        apply()
+    }
+}
+
+fn lock_output<T>(output: &Mutex<T>) -> MutexGuard<T> {
+    output.lock().unwrap_or_else(|err| err.into_inner())
+}
+
+fn error_cause_chain(err: &dyn Error) -> String {
+    let mut causes = vec![err.to_string()];
+    let mut e = err;
+    while let Some(cause) = e.source() {
+        causes.push(cause.to_string());
+        e = cause;
+    }
+    causes.join("\ncaused by: ")
+}
+
+// Process a line of input into a line of JSON output
+fn process_line<G>(line: io::Result<String>, apply: G) -> String
+where
+    G: FnOnce(AlgoIo, RequestInfo) -> Result<AlgoOutput, Box<dyn Error>>,
+{
+    match line {
+        Ok(json_line) => {
+            let (request_id, output) = match parse_request(&json_line) {
+                Ok(mut req) => {
+                    let info = RequestInfo::from(&mut req);
+                    let request_id = info.request_id.clone();
+                    #[cfg(feature = "tracing")]
+                    let _span =
+                        logging::request_span(request_id.as_ref().map(|id| &**id)).entered();
+                    let output = request_input(req).and_then(|input| apply(input, info));
+                    (request_id, output)
+                }
+                Err(err) => (None, Err(err)),
+            };
+            flush_std_pipes();
+            serialize_output(output, request_id)
+        }
+        Err(_) => {
+            let err = line.context("failed to read stdin").unwrap_err();
+            serde_json::to_string(&AlgoFailure::system(&err as &dyn Error)).expect(&format!(
+                "Failed to read stdin and failed to encode the error: {}",
+                err
+            ))
+        }
+    }
+}
+
+fn serialize_output(
+    output: Result<AlgoOutput, Box<dyn Error>>,
+    request_id: Option<String>,
+) -> String {
+    let json_result = match output {
+        Ok(output) => {
+            let mut success = AlgoSuccess::from(output);
+            success.request_id = request_id;
+            serde_json::to_string(&success)
+        }
+        Err(err) => {
+            let mut failure = AlgoFailure::new(&*err as &dyn Error);
+            failure.request_id = request_id;
+            serde_json::to_string(&failure)
+        }
+    };
+
+    json_result.expect("Failed to encode JSON")
+}
+
+fn flush_std_pipes() {
+    let _ = io::stdout().flush();
+    let _ = io::stderr().flush();
+}
+
+fn parse_request(stdin: &str) -> Result<Request, Box<dyn Error>> {
+    let req = serde_json::from_str(stdin).context("Error decoding JSON request")?;
+    Ok(req)
+}
+
+fn request_input(req: Request) -> Result<AlgoIo, Box<dyn Error>> {
+    let Request {
+        data, content_type, ..
+    } = req;
+    let input = match (&*content_type, data) {
+        ("text", Value::String(text)) => AlgoIo::text(text),
+        ("binary", Value::String(ref encoded)) => {
+            let bytes =
+                base64::decode(encoded).context("Error decoding request input as binary")?;
+            AlgoIo::binary(bytes)
+        }
+        ("json", json_obj) => AlgoIo::json(json_obj),
+        (_, _) => {
+            return Err(err_msg(format!("Content type '{}' is invalid", content_type)).into())
+        }
+    };
+    Ok(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::algo::ByteVec;
+    use std::io::Cursor;
+    use std::str;
+    use std::thread;
+
+    fn run_lines<F, IN, OUT, E, E2>(requests: &str, apply: F) -> Vec<Value>
+    where
+        F: FnMut(IN) -> Result<OUT, E>,
+        IN: TryFrom<AlgoIo, Error = E2>,
+        OUT: Into<AlgoOutput>,
+        E: Into<Box<dyn Error>>,
+        E2: Into<Box<dyn Error>>,
+    {
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .run(apply)
+            .unwrap();
+        String::from_utf8(responses)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_runner_text() {
+        let requests = r#"{"content_type":"text","data":"world"}"#;
+        let responses = run_lines(requests, |name: String| {
+            Ok::<_, String>(format!("Hello {}", name))
+        });
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0]["result"], "Hello world");
+        assert_eq!(responses[0]["metadata"]["content_type"], "json");
+    }
+
+    // Writes to /dev/full fail with ENOSPC
+    #[cfg(target_os = "linux")]
+    #[test]
+    fn test_runner_pipe_write_errors() {
+        let requests = r#"{"content_type":"text","data":"world"}"#;
+        let result = Runner::new()
+            .input(Cursor::new(requests))
+            .output_path("/dev/full")
+            .run(|name: String| Ok::<_, String>(name));
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_runner_content_types() {
+        let requests = concat!(
+            r#"{"content_type":"text","data":"a"}"#,
+            "\n",
+            r#"{"content_type":"json","data":"a"}"#,
+            "\n",
+            r#"{"content_type":"binary","data":"YQ=="}"#,
+        );
+        let responses = run_lines(requests, |input: AlgoIo| {
+            Ok::<_, String>(input.kind().to_string())
+        });
+        assert_eq!(responses[0]["result"], "text");
+        assert_eq!(responses[1]["result"], "json");
+        assert_eq!(responses[2]["result"], "binary");
+    }
+
+    #[test]
+    fn test_runner_errors() {
+        let requests = concat!(
+            r#"{"content_type":"json","data":5}"#,
+            "\n",
+            r#"{"content_type":"json","data":"five"}"#,
+            "\n",
+            r#"{"content_type":"bogus","data":5}"#,
+        );
+        let responses = run_lines(requests, |n: u32| match n {
+            0 => Err("zero"),
+            n => Ok(n + 1),
+        });
+        assert_eq!(responses.len(), 3);
+        assert_eq!(responses[0]["result"], 6);
+        assert_eq!(responses[1]["error"]["error_type"], "InputError");
+        assert_eq!(responses[1]["error"]["details"]["expected"], "u32");
+        assert_eq!(responses[2]["error"]["error_type"], "AlgorithmError");
+    }
+
+    #[test]
+    fn test_runner_input_errors() {
+        #[derive(Deserialize)]
+        struct Input {
+            #[allow(dead_code)]
+            titles: Vec<String>,
+        }
+
+        let requests = concat!(
+            r#"{"content_type":"json","data":{"titles":["a",5]}}"#,
+            "\n",
+            r#"{"content_type":"json","data":{}}"#,
+            "\n",
+            r#"{"content_type":"text","data":"abc"}"#,
+        );
+        let responses = run_lines(requests, |_: Input| Ok::<_, String>(()));
+        assert_eq!(responses[0]["error"]["error_type"], "InputError");
+        assert_eq!(responses[0]["error"]["details"]["path"], "titles[1]");
+        assert_eq!(responses[0]["error"]["details"]["expected"], "a string");
+        assert_eq!(
+            responses[0]["error"]["message"],
+            "invalid input at 'titles[1]': invalid type: integer `5`, expected a string"
+        );
+        assert_eq!(
+            responses[1]["error"]["message"],
+            "invalid input: missing field `titles`"
+        );
+        assert!(responses[1]["error"]["details"]["expected"].is_null());
+        assert_eq!(responses[2]["error"]["error_type"], "InputError");
+    }
+
+    #[test]
+    fn test_runner_structured_errors() {
+        use crate::error::AlgoErrorExt;
+        use serde_json::json;
+
+        let requests = concat!(
+            r#"{"content_type":"text","data":"ten"}"#,
+            "\n",
+            r#"{"content_type":"text","data":""}"#,
+        );
+        let responses = run_lines(requests, |input: String| {
+            if input.is_empty() {
+                let err = ApiError::new("InputError", "input is empty")
+                    .with_code("EMPTY")
+                    .with_details(json!({ "min_length": 1 }));
+                return Err(err);
+            }
+            input
+                .parse::<u32>()
+                .with_error_code("InputError", "NOT_A_NUMBER")
+        });
+        assert_eq!(responses[0]["error"]["error_type"], "InputError");
+        assert_eq!(responses[0]["error"]["code"], "NOT_A_NUMBER");
+        assert!(responses[0]["error"]["stacktrace"].is_string());
+        assert_eq!(responses[1]["error"]["message"], "input is empty");
+        assert_eq!(responses[1]["error"]["code"], "EMPTY");
+        assert_eq!(responses[1]["error"]["details"]["min_length"], 1);
+    }
+
+    #[test]
+    fn test_failure_wrapped_api_errors() {
+        use std::fmt;
+
+        #[derive(Debug)]
+        struct Wrapper(crate::error::Error);
+        impl fmt::Display for Wrapper {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                write!(f, "failed to call dependency")
+            }
+        }
+        impl Error for Wrapper {
+            fn source(&self) -> Option<&(dyn Error + 'static)> {
+                Some(&self.0)
+            }
+        }
+
+        let api_err = ApiError::new("UnsupportedError", "unsupported").with_code("NOPE");
+        let client_err = crate::error::Error::from(api_err);
+        let failure = AlgoFailure::new(&client_err);
+        assert_eq!(failure.error.error_type, "UnsupportedError");
+        assert_eq!(failure.error.code.as_ref().unwrap(), "NOPE");
+
+        let failure = AlgoFailure::new(&Wrapper(client_err));
+        assert_eq!(failure.error.error_type, "UnsupportedError");
+        assert_eq!(failure.error.message, "unsupported");
+
+        let failure = AlgoFailure::new(&*Box::<dyn Error>::from("plain"));
+        assert_eq!(failure.error.error_type, "AlgorithmError");
+    }
+
+    #[test]
+    fn test_runner_panics() {
+        let requests = concat!(
+            r#"{"content_type":"json","data":0}"#,
+            "\n",
+            r#"{"content_type":"json","data":2}"#,
+        );
+        let responses = run_lines(requests, |n: u32| {
+            if n == 0 {
+                panic!("cannot divide by zero");
+            }
+            Ok::<_, String>(10 / n)
+        });
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["error"]["error_type"], "AlgorithmError");
+        assert_eq!(
+            responses[0]["error"]["message"],
+            "algorithm panicked: cannot divide by zero"
+        );
+        assert!(responses[0]["error"]["stacktrace"].is_string());
+        assert_eq!(responses[1]["result"], 5);
+    }
+
+    #[test]
+    fn test_runner_output_metadata() {
+        let requests = r#"{"content_type":"text","data":"logo"}"#;
+        let responses = run_lines(requests, |_: String| {
+            let output = AlgoOutput::new(ByteVec::from(vec![0u8, 1, 2]))
+                .with_mime_type(mime::IMAGE_PNG)
+                .with_alert("image was resized")
+                .with_metadata("width", 64)
+                .with_metadata("content_type", "ignored");
+            Ok::<_, String>(output)
+        });
+        let metadata = &responses[0]["metadata"];
+        assert_eq!(responses[0]["result"], "AAEC");
+        assert_eq!(metadata["content_type"], "binary");
+        assert_eq!(metadata["mime_type"], "image/png");
+        assert_eq!(metadata["alerts"][0], "image was resized");
+        assert_eq!(metadata["width"], 64);
+    }
+
+    #[test]
+    fn test_runner_stream() {
+        let requests = concat!(
+            r#"{"content_type":"binary","data":"aGVsbG8=","request_id":"a"}"#,
+            "\n",
+            r#"{"content_type":"text","data":"world"}"#,
+            "\n",
+            r#"{"content_type":"json","data":{}}"#,
+        );
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .run_stream(|mut input: BinaryInput| {
+                let mut bytes = Vec::new();
+                input.read_to_end(&mut bytes)?;
+                bytes.make_ascii_uppercase();
+                Ok::<_, io::Error>(Cursor::new(bytes))
+            })
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(responses)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses[0]["result"], "SEVMTE8=");
+        assert_eq!(responses[0]["metadata"]["content_type"], "binary");
+        assert_eq!(responses[0]["request_id"], "a");
+        assert_eq!(responses[1]["result"], base64::encode("WORLD"));
+        assert_eq!(responses[2]["error"]["error_type"], "InputError");
+    }
+
+    // Takes the response buffer since the input passed to `apply` borrows the runner
+    fn run_stream_lines<'a, F, OUT>(requests: &'a str, responses: &'a mut Vec<u8>, apply: F)
+    where
+        F: FnMut(BinaryInput<'a>) -> Result<OUT, io::Error>,
+        OUT: Read,
+    {
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(responses)
+            .run_stream(apply)
+            .unwrap();
+    }
+
+    fn parse_lines(responses: &[u8]) -> Vec<Value> {
+        str::from_utf8(responses)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect()
+    }
+
+    #[test]
+    fn test_runner_stream_envelopes() {
+        let requests = concat!(
+            // `data` before `content_type` can't be streamed, and `request_id` may follow `data`
+            r#"{"data":"aGk=","content_type":"binary","request_id":"a"}"#,
+            "\n",
+            // Escapes and whitespace within the base64 data
+            r#" { "content_type" : "binary", "data" : "aGVs\nbG8\/", "request_id" : "b" } "#,
+            "\n",
+            r#"{"content_type":"text","data":"caf\u00e9 \ud83d\ude00","timeout":5}"#,
+            "\n",
+            r#"{"content_type":"binary","data":"aGVsbG8=","request_id":"c",}"#,
+            "\n",
+            "\n",
+            r#"{"content_type":"binary","data":"aGVsbG8=","request_id":"d"}"#,
+        );
+        let mut responses = Vec::new();
+        run_stream_lines(requests, &mut responses, |mut input| {
+            let mut bytes = Vec::new();
+            input.read_to_end(&mut bytes)?;
+            Ok(Cursor::new(bytes))
+        });
+        let responses = parse_lines(&responses);
+        assert_eq!(responses.len(), 6);
+        assert_eq!(responses[0]["result"], base64::encode("hi"));
+        assert_eq!(responses[0]["request_id"], "a");
+        assert_eq!(responses[1]["result"], "aGVsbG8/");
+        assert_eq!(responses[1]["request_id"], "b");
+        assert_eq!(responses[2]["result"], base64::encode("café 😀"));
+        assert!(responses[3]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .contains("Error decoding JSON request"));
+        assert!(responses[4]["error"].is_object());
+        assert_eq!(responses[5]["result"], "aGVsbG8=");
+        assert_eq!(responses[5]["request_id"], "d");
+    }
+
+    #[test]
+    fn test_runner_stream_large_input() {
+        let bytes: Vec<u8> = (0..300_000u32).map(|n| (n * 7 % 251) as u8).collect();
+        // Wrapped at 76 characters like MIME base64, so 4-character groups span the decoded chunks
+        let encoded = base64::encode(&bytes);
+        let lines: Vec<&str> = encoded
+            .as_bytes()
+            .chunks(76)
+            .map(|line| str::from_utf8(line).unwrap())
+            .collect();
+        let encoded = lines.join("\\n");
+        let requests = format!(r#"{{"content_type":"binary","data":"{}"}}"#, encoded);
+        let mut responses = Vec::new();
+        run_stream_lines(&requests, &mut responses, |mut input| {
+            let mut bytes = Vec::new();
+            input.read_to_end(&mut bytes)?;
+            Ok(Cursor::new(bytes))
+        });
+        let responses = parse_lines(&responses);
+        assert_eq!(responses[0]["result"], base64::encode(&bytes));
+    }
+
+    #[test]
+    fn test_runner_stream_partial_reads() {
+        struct FailingOutput;
+
+        impl Read for FailingOutput {
+            fn read(&mut self, _: &mut [u8]) -> io::Result<usize> {
+                Err(io::Error::new(io::ErrorKind::Other, "disk unplugged"))
+            }
+        }
+
+        let requests = concat!(
+            r#"{"content_type":"binary","data":"aGVsbG8gd29ybGQ=","request_id":"a"}"#,
+            "\n",
+            r#"{"content_type":"binary","data":"aGVsbG8=","request_id":"b"}"#,
+            "\n",
+            r#"{"content_type":"text","data":"fail","request_id":"c"}"#,
+            "\n",
+            r#"{"content_type":"text","data":"done"}"#,
+        );
+        let mut count = 0;
+        let mut responses = Vec::new();
+        run_stream_lines(requests, &mut responses, |input| {
+            count += 1;
+            // The output reads the input lazily, and doesn't read all of it
+            let output: Box<dyn Read> = match count {
+                1 => Box::new(input.take(5)),
+                2 => Box::new(io::empty()),
+                3 => Box::new(FailingOutput),
+                _ => Box::new(input),
+            };
+            Ok(output)
+        });
+        let responses = parse_lines(&responses);
+        assert_eq!(responses.len(), 4);
+        assert_eq!(responses[0]["result"], base64::encode("hello"));
+        assert_eq!(responses[0]["request_id"], "a");
+        assert_eq!(responses[1]["result"], "");
+        assert_eq!(responses[1]["request_id"], "b");
+        assert!(responses[2]["error"]["message"]
+            .as_str()
+            .unwrap()
+            .starts_with("Cannot read handler output"));
+        assert_eq!(responses[2]["request_id"], "c");
+        assert_eq!(responses[3]["result"], base64::encode("done"));
+    }
+
+    #[test]
+    fn test_runner_handler_lifecycle() {
+        use std::cell::RefCell;
+        use std::rc::Rc;
+
+        struct Counter {
+            count: u32,
+            stages: Rc<RefCell<Vec<&'static str>>>,
+        }
+
+        impl Handler for Counter {
+            type Input = String;
+            type Output = u32;
+            type Error = String;
+
+            fn load(&mut self) -> Result<(), Box<dyn Error>> {
+                self.stages.borrow_mut().push("load");
+                Ok(())
+            }
+
+            fn warmup(&mut self) -> Result<(), Box<dyn Error>> {
+                self.stages.borrow_mut().push("warmup");
+                self.count = 10;
+                Ok(())
+            }
+
+            fn apply(&mut self, _: String) -> Result<u32, String> {
+                self.stages.borrow_mut().push("apply");
+                self.count += 1;
+                Ok(self.count)
+            }
+
+            fn shutdown(&mut self) -> Result<(), Box<dyn Error>> {
+                self.stages.borrow_mut().push("shutdown");
+                Ok(())
+            }
+        }
+
+        let stages = Rc::new(RefCell::new(Vec::new()));
+        let requests = "{\"content_type\":\"text\",\"data\":\"a\"}\n".repeat(2);
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .run_handler(Counter {
+                count: 0,
+                stages: stages.clone(),
+            })
+            .unwrap();
+
+        let responses = String::from_utf8(responses).unwrap();
+        assert_eq!(
+            responses.lines().last().unwrap(),
+            r#"{"result":12,"metadata":{"content_type":"json"}}"#
+        );
+        assert_eq!(
+            *stages.borrow(),
+            vec!["load", "warmup", "apply", "apply", "shutdown"]
+        );
+
+        // The handler is still shut down if a response can't be written
+        struct ClosedPipe;
+
+        impl Write for ClosedPipe {
+            fn write(&mut self, _: &[u8]) -> io::Result<usize> {
+                Err(io::ErrorKind::BrokenPipe.into())
+            }
+
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        stages.borrow_mut().clear();
+        let result = Runner::new()
+            .input(Cursor::new("{\"content_type\":\"text\",\"data\":\"a\"}\n"))
+            .output(ClosedPipe)
+            .run_handler(Counter {
+                count: 0,
+                stages: stages.clone(),
+            });
+        assert!(result.is_err());
+        assert_eq!(
+            *stages.borrow(),
+            vec!["load", "warmup", "apply", "shutdown"]
+        );
+    }
+
+    #[test]
+    fn test_runner_context() {
+        let requests = concat!(
+            r#"{"content_type":"text","data":"a","request_id":"r1","caller":"alice","timeout":30}"#,
+            "\n",
+            r#"{"content_type":"text","data":"b"}"#,
+        );
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .run_with_context(|input: String, ctx: &Context| {
+                Ok::<_, String>(format!(
+                    "{} {:?} {:?} {:?}",
+                    input,
+                    ctx.request_id(),
+                    ctx.caller(),
+                    ctx.timeout().map(|t| t.as_secs())
+                ))
+            })
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(responses)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(
+            responses[0]["result"],
+            r#"a Some("r1") Some("alice") Some(30)"#
+        );
+        assert_eq!(responses[0]["request_id"], "r1");
+        assert_eq!(responses[1]["result"], "b None None None");
+    }
+
+    #[test]
+    fn test_runner_timeouts() {
+        let requests = concat!(
+            r#"{"content_type":"json","data":true,"request_id":"slow"}"#,
+            "\n",
+            r#"{"content_type":"json","data":false,"request_id":"fast"}"#,
+        );
+        let apply = |slow: bool, ctx: &Context| {
+            if slow {
+                while !ctx.cancellation_token().is_cancelled() {
+                    thread::sleep(Duration::from_millis(5));
+                }
+            }
+            ctx.cancellation_token().check()?;
+            Ok::<_, ApiError>(ctx.request_id().unwrap().to_owned())
+        };
+
+        // Cooperative cancellation reports the error returned by `apply`
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .default_timeout(Duration::from_millis(50))
+            .run_with_context(apply)
+            .unwrap();
+        let responses = String::from_utf8(responses).unwrap();
+        let responses: Vec<Value> = responses
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["error"]["error_type"], "TimeoutError");
+        assert_eq!(responses[1]["result"], "fast");
+
+        // Enforced timeouts respond before `apply` returns, and discard its result
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .default_timeout(Duration::from_millis(50))
+            .enforce_timeouts(true)
+            .run_with_context(|slow: bool, ctx: &Context| {
+                let res = apply(slow, ctx);
+                thread::sleep(Duration::from_millis(20));
+                res.or_else(|_| Ok::<_, ApiError>("late".into()))
+            })
+            .unwrap();
+        let responses: Vec<Value> = String::from_utf8(responses)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 2);
+        assert_eq!(responses[0]["error"]["error_type"], "TimeoutError");
+        assert_eq!(responses[0]["request_id"], "slow");
+        assert_eq!(responses[1]["result"], "fast");
+    }
+
+    #[test]
+    #[should_panic(expected = "uncaught")]
+    fn test_runner_timeouts_uncaught_panic() {
+        // The watchdog is stopped, so the panic isn't blocked joining it
+        Runner::new()
+            .input(Cursor::new(r#"{"content_type":"json","data":1}"#))
+            .output(io::sink())
+            .default_timeout(Duration::from_secs(60))
+            .enforce_timeouts(true)
+            .catch_panics(false)
+            .run(|_: u32| -> Result<u32, String> { panic!("uncaught") })
+            .unwrap();
+    }
+
+    #[test]
+    fn test_runner_load_state() {
+        let requests = "{\"content_type\":\"text\",\"data\":\"a\"}\n".repeat(3);
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .load_and_run(
+                || Ok::<_, String>(0),
+                |_: String, count: &mut u32| {
+                    *count += 1;
+                    Ok::<_, String>(*count)
+                },
+            )
+            .unwrap();
+        let responses = String::from_utf8(responses).unwrap();
+        assert_eq!(
+            responses.lines().last().unwrap(),
+            r#"{"result":3,"metadata":{"content_type":"json"}}"#
+        );
+    }
+
+    #[test]
+    fn test_runner_concurrent() {
+        let requests: String = (0..20)
+            .map(|i| format!("{{\"content_type\":\"json\",\"data\":{}}}\n", i))
+            .collect();
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .load_and_run_concurrent(
+                4,
+                || Ok::<_, String>(100),
+                |n: u32, offset: &u32| {
+                    // Later requests finish first
+                    thread::sleep(Duration::from_millis(u64::from(20 - n)));
+                    Ok::<_, String>(n + *offset)
+                },
+            )
+            .unwrap();
+
+        let responses: Vec<Value> = String::from_utf8(responses)
+            .unwrap()
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(responses.len(), 20);
+        for (n, res) in responses.iter().enumerate() {
+            assert_eq!(res["result"], n as u64 + 100);
+        }
+    }
+
+    #[cfg(feature = "tracing")]
+    #[test]
+    fn test_runner_logging() {
+        #[derive(Clone)]
+        struct Logs(Arc<Mutex<Vec<u8>>>);
+        impl Write for Logs {
+            fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+                self.0.lock().unwrap().write(buf)
+            }
+            fn flush(&mut self) -> io::Result<()> {
+                Ok(())
+            }
+        }
+
+        let logs = Logs(Arc::new(Mutex::new(Vec::new())));
+        let writer = logs.clone();
+        let subscriber = tracing_subscriber::fmt()
+            .with_writer(move || writer.clone())
+            .with_ansi(false)
+            .finish();
+        let requests = r#"{"content_type":"text","data":"world","request_id":"req-7"}"#;
+        tracing::subscriber::with_default(subscriber, || {
+            run_lines(requests, |name: String| {
+                tracing::info!("greeting {}", name);
+                Ok::<_, String>(name)
+            })
+        });
+
+        let logs = String::from_utf8(logs.0.lock().unwrap().clone()).unwrap();
+        assert!(logs.contains(r#"request{request_id="req-7"}"#), "{}", logs);
+        assert!(logs.contains("greeting world"), "{}", logs);
+    }
+
+    #[cfg(feature = "async")]
+    #[test]
+    fn test_runner_async() {
+        let requests = r#"{"content_type":"json","data":41}"#;
+        let mut responses = Vec::new();
+        Runner::new()
+            .input(Cursor::new(requests))
+            .output(&mut responses)
+            .load_and_run_async(
+                || async { Ok::<_, String>(1) },
+                |n: u32, step: &mut u32| {
+                    let step = *step;
+                    async move { Ok::<_, String>(n + step) }
+                },
+            )
+            .unwrap();
+        let responses = String::from_utf8(responses).unwrap();
+        assert_eq!(
+            responses.trim(),
+            r#"{"result":42,"metadata":{"content_type":"json"}}"#
+        );
+    }
+}
@@ -0,0 +1,80 @@
+use crate::prelude::AlgoIo;
+use mime::Mime;
+use serde_json::{Map, Value};
+
+/// Algorithm output with additional response metadata
+///
+/// Any handler output that converts into `AlgoIo` can be returned directly,
+/// but returning an `AlgoOutput` also attaches alerts, a MIME type for binary output,
+/// or custom key/values to the response `metadata`.
+///
+/// ```rust
+/// use algorithmia::algo::ByteVec;
+/// use algorithmia::handler::AlgoOutput;
+///
+/// fn apply(input: String) -> Result<AlgoOutput, String> {
+///     let png = ByteVec::from(render(&input));
+///     Ok(AlgoOutput::new(png)
+///         .with_mime_type(mime::IMAGE_PNG)
+///         .with_alert("falling back to the default font")
+///         .with_metadata("width", 640))
+/// }
+/// # fn render(_: &str) -> Vec<u8> { Vec::new() }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AlgoOutput {
+    pub(crate) result: AlgoIo,
+    pub(crate) mime_type: Option<Mime>,
+    pub(crate) alerts: Vec<String>,
+    pub(crate) metadata: Map<String, Value>,
+}
+
+impl AlgoOutput {
+    /// Instantiate output with no additional metadata
+    pub fn new<T: Into<AlgoIo>>(result: T) -> AlgoOutput {
+        AlgoOutput {
+            result: result.into(),
+            mime_type: None,
+            alerts: Vec::new(),
+            metadata: Map::new(),
+        }
+    }
+
+    /// Builder method to add an alert (e.g. a warning) to the response
+    pub fn with_alert<S: Into<String>>(mut self, alert: S) -> AlgoOutput {
+        self.alerts.push(alert.into());
+        self
+    }
+
+    /// Builder method to set the MIME type of binary output
+    ///
+    /// The response `content_type` remains `binary`, and the MIME type is included as `mime_type`.
+    pub fn with_mime_type(mut self, mime_type: Mime) -> AlgoOutput {
+        self.mime_type = Some(mime_type);
+        self
+    }
+
+    /// Builder method to add a custom key/value to the response metadata
+    ///
+    /// Keys that collide with standard metadata fields
+    /// (`content_type`, `duration`, `mime_type`, and `alerts`) are ignored.
+    pub fn with_metadata<K: Into<String>, V: Into<Value>>(
+        mut self,
+        key: K,
+        value: V,
+    ) -> AlgoOutput {
+        self.metadata.insert(key.into(), value.into());
+        self
+    }
+
+    /// The algorithm result
+    pub fn result(&self) -> &AlgoIo {
+        &self.result
+    }
+}
+
+impl<T: Into<AlgoIo>> From<T> for AlgoOutput {
+    fn from(result: T) -> AlgoOutput {
+        AlgoOutput::new(result)
+    }
+}
@@ -0,0 +1,95 @@
+//! Reporting panics in `apply` as algorithm failures
+
+use crate::error::ApiError;
+use backtrace::Backtrace;
+use std::any::Any;
+use std::cell::{Cell, RefCell};
+use std::error::Error;
+use std::panic::{self, AssertUnwindSafe};
+use std::sync::Once;
+
+static INSTALL_HOOK: Once = Once::new();
+
+thread_local! {
+    // Whether this thread is inside `catch_panic`, so the hook ignores panics elsewhere
+    static IN_APPLY: Cell<bool> = Cell::new(false);
+    // Backtrace of the most recent panic in `apply` on this thread, captured by the panic hook
+    static PANIC_BACKTRACE: RefCell<Option<Backtrace>> = RefCell::new(None);
+}
+
+/// Call `apply`, reporting a panic as an `AlgorithmError` instead of unwinding
+pub(super) fn catch_panic<T, G>(apply: G) -> Result<T, Box<dyn Error>>
+where
+    G: FnOnce() -> Result<T, Box<dyn Error>>,
+{
+    install_hook();
+    PANIC_BACKTRACE.with(|bt| bt.borrow_mut().take());
+    let was_in_apply = IN_APPLY.with(|in_apply| in_apply.replace(true));
+    let result = panic::catch_unwind(AssertUnwindSafe(apply));
+    IN_APPLY.with(|in_apply| in_apply.set(was_in_apply));
+    match result {
+        Ok(output) => output,
+        Err(payload) => {
+            // Symbolizing is slow, so it is deferred until the backtrace is reported
+            let stacktrace = PANIC_BACKTRACE
+                .with(|bt| bt.borrow_mut().take())
+                .map(|mut bt| {
+                    bt.resolve();
+                    format!("{:?}", bt)
+                });
+            let message = format!("algorithm panicked: {}", panic_message(&*payload));
+            Err(Box::new(ApiError {
+                message: message,
+                error_type: Some("AlgorithmError".into()),
+                stacktrace: stacktrace,
+                code: None,
+                details: None,
+                _dummy: (),
+            }))
+        }
+    }
+}
+
+// The backtrace is only available while panicking, so record it from a hook
+// that still defers to the previous hook (which prints the panic to stderr).
+// Panics outside of `catch_panic` (e.g. on other threads) go straight to the previous hook.
+fn install_hook() {
+    INSTALL_HOOK.call_once(|| {
+        let prev_hook = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            if IN_APPLY.with(Cell::get) {
+                let bt = Backtrace::new_unresolved();
+                PANIC_BACKTRACE.with(|cell| *cell.borrow_mut() = Some(bt));
+            }
+            prev_hook(info);
+        }));
+    });
+}
+
+fn panic_message(payload: &(dyn Any + Send)) -> &str {
+    if let Some(msg) = payload.downcast_ref::<&'static str>() {
+        msg
+    } else if let Some(msg) = payload.downcast_ref::<String>() {
+        msg
+    } else {
+        "Box<Any>"
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_ignores_panics_outside_apply() {
+        install_hook();
+        assert!(panic::catch_unwind(|| panic!("outside apply")).is_err());
+        assert!(PANIC_BACKTRACE.with(|bt| bt.borrow().is_none()));
+
+        let err = catch_panic::<(), _>(|| panic!("inside apply")).unwrap_err();
+        let err = err.downcast_ref::<ApiError>().unwrap();
+        assert_eq!(err.message, "algorithm panicked: inside apply");
+        assert!(err.stacktrace.is_some());
+        assert!(!IN_APPLY.with(Cell::get));
+    }
+}
@@ -0,0 +1,209 @@
+//! Local development server for handlers [feature = "serve"]
+
+use super::{
+    call_apply, guard_apply, lock_output, request_input, AlgoFailure, AlgoOutput, AlgoSuccess,
+    Request,
+};
+use crate::algo::{ByteVec, TryFrom};
+use crate::error::{err_msg, ResultExt};
+use crate::prelude::AlgoIo;
+use hyper::header::{HeaderValue, CONTENT_TYPE};
+use hyper::rt::{self, Future, Stream};
+use hyper::service::service_fn;
+use hyper::{Body, Method, Response, Server, StatusCode};
+use mime::Mime;
+use serde_json::Value;
+use std::error::Error;
+use std::net::ToSocketAddrs;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+/// Path prefix that algorithms are served under, e.g. `/v1/algo/local/MyAlgo`
+const LOCAL_ALGO_PATH: &'static str = "/v1/algo/local/";
+
+/// Serves a handler over HTTP for local development [feature = "serve"]
+///
+/// The `apply` function accepts the same functions as [`handler::run`](fn.run.html),
+/// but is exposed at `POST /v1/algo/local/<name>` and responds with the same
+/// response envelope as the Algorithmia API. The request `Content-Type` determines
+/// the input: `application/json` is JSON, `text/*` is text, and anything else is binary.
+///
+/// This blocks until the server exits.
+///
+/// ```no_run
+/// use algorithmia::prelude::*;
+///
+/// fn apply(name: String) -> Result<String, String> {
+///     Ok(format!("Hello {}", name))
+/// }
+///
+/// fn main() -> Result<(), Box<std::error::Error>> {
+///     handler::serve("127.0.0.1:8080", apply)
+/// }
+/// ```
+///
+/// Then call it from another project with a client pointed at the local server:
+///
+/// ```no_run
+/// use algorithmia::Algorithmia;
+///
+/// let client = Algorithmia::client_with_url("", "http://127.0.0.1:8080")?;
+/// let output = client.algo("local/Hello").pipe("world")?;
+/// # Ok::<(), Box<std::error::Error>>(())
+/// ```
+pub fn serve<A, F, IN, OUT, E, E2>(addr: A, apply: F) -> Result<(), Box<dyn Error>>
+where
+    A: ToSocketAddrs,
+    F: FnMut(IN) -> Result<OUT, E> + Send + 'static,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    let addr = addr
+        .to_socket_addrs()
+        .context("invalid server address")?
+        .next()
+        .ok_or_else(|| err_msg("server address did not resolve"))?;
+    let apply = Arc::new(Mutex::new(apply));
+
+    let server = Server::try_bind(&addr)?.serve(move || {
+        let apply = apply.clone();
+        service_fn(move |req: hyper::Request<Body>| {
+            let apply = apply.clone();
+            let method = req.method().clone();
+            let path = req.uri().path().to_owned();
+            let content_type = req
+                .headers()
+                .get(CONTENT_TYPE)
+                .and_then(|val| val.to_str().ok())
+                .and_then(|val| val.parse::<Mime>().ok());
+
+            req.into_body().concat2().map(move |body| {
+                if method != Method::POST {
+                    return failure(StatusCode::METHOD_NOT_ALLOWED, "method not allowed");
+                }
+                if !path.starts_with(LOCAL_ALGO_PATH) {
+                    return failure(StatusCode::NOT_FOUND, "algorithm not found");
+                }
+
+                call_http(&apply, content_type, body.to_vec())
+            })
+        })
+    });
+
+    println!(
+        "Serving algorithm at http://{}{}<name>",
+        server.local_addr(),
+        LOCAL_ALGO_PATH
+    );
+    rt::run(server.map_err(|err| eprintln!("server error: {}", err)));
+    Ok(())
+}
+
+// Call `apply` for the body of an HTTP request, guarding against panics in the same way as
+// `handler::run` so that a panic in one request is reported instead of poisoning the mutex
+fn call_http<F, IN, OUT, E, E2>(
+    apply: &Mutex<F>,
+    content_type: Option<Mime>,
+    body: Vec<u8>,
+) -> Response<Body>
+where
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    let start = Instant::now();
+    let mut apply = lock_output(apply);
+    let output = http_input(content_type, body)
+        .and_then(|input| guard_apply(true, || call_apply(&mut *apply, input)));
+    match output {
+        Ok(output) => {
+            let mut success = AlgoSuccess::from(output);
+            let elapsed = start.elapsed();
+            success.metadata.duration =
+                Some(elapsed.as_secs() as f64 + f64::from(elapsed.subsec_nanos()) * 1e-9);
+            json_response(StatusCode::OK, &success)
+        }
+        Err(err) => json_response(
+            StatusCode::BAD_REQUEST,
+            &AlgoFailure::new(&*err as &dyn Error),
+        ),
+    }
+}
+
+// Build handler input from an HTTP request in the same way as stdin requests
+fn http_input(content_type: Option<Mime>, body: Vec<u8>) -> Result<AlgoIo, Box<dyn Error>> {
+    let (data, content_type) = match content_type {
+        Some(ref mime) if mime.essence_str() == "application/json" => {
+            let data = serde_json::from_slice(&body).context("Error decoding JSON request")?;
+            (data, "json")
+        }
+        Some(ref mime) if mime.type_() == mime::TEXT => {
+            let text = String::from_utf8(body).map_err(|_| err_msg("Text input is not UTF-8"))?;
+            (Value::String(text), "text")
+        }
+        _ => return Ok(AlgoIo::from(ByteVec::from(body))),
+    };
+    request_input(Request {
+        data,
+        content_type: content_type.into(),
+        request_id: None,
+        caller: None,
+        timeout: None,
+    })
+}
+
+fn failure(status: StatusCode, message: &str) -> Response<Body> {
+    json_response(status, &AlgoFailure::system(&err_msg(message)))
+}
+
+fn json_response<S: serde::Serialize>(status: StatusCode, body: &S) -> Response<Body> {
+    let json = serde_json::to_vec(body).expect("Failed to encode JSON");
+    let mut res = Response::new(Body::from(json));
+    *res.status_mut() = status;
+    res.headers_mut()
+        .insert(CONTENT_TYPE, HeaderValue::from_static("application/json"));
+    res
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_http_input_content_types() {
+        let json = http_input(Some(mime::APPLICATION_JSON), b"[1,2]".to_vec()).unwrap();
+        assert_eq!(json.decode::<Vec<u32>>().unwrap(), vec![1, 2]);
+
+        let text = http_input(Some(mime::TEXT_PLAIN_UTF_8), b"hello".to_vec()).unwrap();
+        assert_eq!(text.as_string(), Some("hello"));
+
+        let binary = http_input(None, vec![0, 1, 2]).unwrap();
+        assert_eq!(binary.as_bytes(), Some(&[0u8, 1, 2][..]));
+    }
+
+    #[test]
+    fn test_call_http_after_panic() {
+        let apply = Mutex::new(|input: String| -> Result<String, String> {
+            if input == "boom" {
+                panic!("boom");
+            }
+            Ok(input)
+        });
+        let text = Some(mime::TEXT_PLAIN);
+
+        let res = call_http(&apply, text.clone(), b"boom".to_vec());
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+        let res = call_http(&apply, text, b"ok".to_vec());
+        assert_eq!(res.status(), StatusCode::OK);
+        assert!(!apply.is_poisoned());
+    }
+
+    #[test]
+    fn test_http_input_invalid_json() {
+        assert!(http_input(Some(mime::APPLICATION_JSON), b"{".to_vec()).is_err());
+    }
+}
@@ -0,0 +1,631 @@
+//! Streaming binary input and output for handlers
+
+use super::AlgoSuccess;
+use crate::error::{err_msg, ApiError, ResultExt};
+use serde::de::IgnoredAny;
+use serde_json::Value;
+use std::cell::RefCell;
+use std::cmp;
+use std::env;
+use std::error::Error;
+use std::fs::{self, File, OpenOptions};
+use std::io::{self, BufRead, BufWriter, Cursor, Read, Seek, SeekFrom, Write};
+use std::path::PathBuf;
+use std::process;
+use std::rc::Rc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const NULL_RESULT: &'static str = r#"{"result":null"#;
+
+// Number of `data` bytes read at a time
+const DECODE_CHUNK_SIZE: usize = 64 * 1024;
+
+// Distinguishes the spool files of concurrent runners in the same process
+static SPOOL_COUNT: AtomicUsize = AtomicUsize::new(0);
+
+/// Request input that is read as a stream of bytes
+///
+/// The request's `data` is read from the request stream as `BinaryInput` is read, so neither
+/// the encoded nor the decoded input is ever held in memory all at once. Binary input is
+/// base64-decoded (ignoring any whitespace in the base64 data), and text input is read as UTF-8 bytes.
+///
+/// See [`handler::run_stream`](fn.run_stream.html).
+pub struct BinaryInput<'a> {
+    data: Data<'a>,
+    encoded: bool,
+    chunk: Vec<u8>,
+    // Base64 characters that don't yet make up a whole 4-character group
+    pending: Vec<u8>,
+    buf: Vec<u8>,
+    buf_pos: usize,
+}
+
+enum Data<'a> {
+    // The rest of the `data` string of request `request` in the request stream
+    Stream(Rc<RefCell<RequestReader<'a>>>, u64),
+    // A `data` string that was read before the request's `content_type`, so couldn't be streamed
+    Buffered(Cursor<Vec<u8>>),
+}
+
+impl<'a> BinaryInput<'a> {
+    fn new(data: Data<'a>, encoded: bool) -> BinaryInput<'a> {
+        BinaryInput {
+            data: data,
+            encoded: encoded,
+            chunk: Vec::new(),
+            pending: Vec::new(),
+            buf: Vec::new(),
+            buf_pos: 0,
+        }
+    }
+
+    /// Returns true if the request was binary (rather than text)
+    pub fn is_binary(&self) -> bool {
+        self.encoded
+    }
+
+    // Read the next chunk of input into `buf`, returning false at the end of the input
+    fn fill_buf(&mut self) -> io::Result<bool> {
+        self.buf.clear();
+        self.buf_pos = 0;
+        while self.buf.is_empty() {
+            self.chunk.clear();
+            let more = self.data.read_chunk(&mut self.chunk)?;
+            if !self.encoded {
+                self.buf.extend_from_slice(&self.chunk);
+            } else {
+                let chunk = self.chunk.iter().filter(|b| !b.is_ascii_whitespace());
+                self.pending.extend(chunk);
+                // Only decode whole groups until the last chunk, since a group may span chunks
+                let end = if more {
+                    self.pending.len() / 4 * 4
+                } else {
+                    self.pending.len()
+                };
+                base64::decode_config_buf(&self.pending[..end], base64::STANDARD, &mut self.buf)
+                    .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+                self.pending.drain(..end);
+            }
+            if !more {
+                break;
+            }
+        }
+        Ok(!self.buf.is_empty())
+    }
+}
+
+impl<'a> Read for BinaryInput<'a> {
+    fn read(&mut self, out: &mut [u8]) -> io::Result<usize> {
+        if self.buf_pos >= self.buf.len() && !self.fill_buf()? {
+            return Ok(0);
+        }
+        let n = (&self.buf[self.buf_pos..]).read(out)?;
+        self.buf_pos += n;
+        Ok(n)
+    }
+}
+
+impl<'a> Data<'a> {
+    // Append the next chunk of the unescaped `data` string to `out`, returning false once it ends
+    fn read_chunk(&mut self, out: &mut Vec<u8>) -> io::Result<bool> {
+        match self {
+            Data::Stream(reader, request) => {
+                let mut reader = reader.borrow_mut();
+                if reader.request != *request {
+                    return Err(io::Error::new(
+                        io::ErrorKind::Other,
+                        "request input was read after its request completed",
+                    ));
+                }
+                reader.read_data(out, DECODE_CHUNK_SIZE)
+            }
+            Data::Buffered(data) => {
+                Read::by_ref(data)
+                    .take(DECODE_CHUNK_SIZE as u64)
+                    .read_to_end(out)?;
+                Ok((data.position() as usize) < data.get_ref().len())
+            }
+        }
+    }
+}
+
+/// The fields of a request that has been read up to its `data`
+#[derive(Default)]
+pub(super) struct Envelope {
+    content_type: Option<String>,
+    pub(super) request_id: Option<String>,
+    // A `data` value that couldn't be streamed, as raw JSON
+    data: Option<Vec<u8>>,
+    streaming: bool,
+    // Number of members read so far
+    members: usize,
+}
+
+/// Newline-delimited requests, each read from the stream up to its `data` string
+/// so that the string can be read incrementally by `apply`
+pub(super) struct RequestStream<'a> {
+    reader: Rc<RefCell<RequestReader<'a>>>,
+}
+
+impl<'a> RequestStream<'a> {
+    pub(super) fn new(reader: Box<dyn BufRead + 'a>) -> RequestStream<'a> {
+        RequestStream {
+            reader: Rc::new(RefCell::new(RequestReader {
+                reader: reader,
+                request: 0,
+                in_data: false,
+            })),
+        }
+    }
+
+    /// Read the next request up to its `data`, or return `None` at the end of the stream
+    ///
+    /// A malformed request is skipped to the end of its line and returned as an error.
+    pub(super) fn next_request(&self) -> Option<Result<Envelope, Box<dyn Error>>> {
+        let mut reader = self.reader.borrow_mut();
+        let mut env = Envelope::default();
+        let result = reader
+            .start_request()
+            .and_then(|started| match started {
+                true => reader.read_members(&mut env).map(|_| Some(env)),
+                false => Ok(None),
+            })
+            .context("Error decoding JSON request");
+        match result {
+            Ok(env) => env.map(Ok),
+            Err(err) => {
+                reader.skip_line();
+                Some(Err(err.into()))
+            }
+        }
+    }
+
+    /// The input passed to `apply` for the request
+    pub(super) fn input(&self, env: &Envelope) -> Result<BinaryInput<'a>, Box<dyn Error>> {
+        let content_type = match env.content_type {
+            Some(ref content_type) => content_type,
+            None => return Err(missing_field("content_type")),
+        };
+        let encoded = match &**content_type {
+            "text" => false,
+            "binary" => true,
+            content_type => {
+                return Err(Box::new(ApiError {
+                    message: format!("Content type '{}' cannot be read as a stream", content_type),
+                    error_type: Some("InputError".into()),
+                    stacktrace: None,
+                    code: None,
+                    details: None,
+                    _dummy: (),
+                }))
+            }
+        };
+        let data = if env.streaming {
+            let request = self.reader.borrow().request;
+            Data::Stream(self.reader.clone(), request)
+        } else {
+            match env.data {
+                Some(ref raw) => {
+                    let data: String =
+                        serde_json::from_slice(raw).context("Error decoding JSON request")?;
+                    Data::Buffered(Cursor::new(data.into_bytes()))
+                }
+                None => return Err(missing_field("data")),
+            }
+        };
+        Ok(BinaryInput::new(data, encoded))
+    }
+
+    /// Read the rest of the request (including any `data` that `apply` didn't read)
+    ///
+    /// A malformed request is skipped to the end of its line and returned as an error.
+    pub(super) fn finish_request(&self, env: &mut Envelope) -> Result<(), Box<dyn Error>> {
+        if !env.streaming {
+            return Ok(());
+        }
+        let mut reader = self.reader.borrow_mut();
+        let mut skipped = Vec::new();
+        let result = reader
+            .skip_data(&mut skipped)
+            .and_then(|_| reader.read_members(env))
+            .context("Error decoding JSON request");
+        if result.is_err() {
+            reader.skip_line();
+        }
+        Ok(result?)
+    }
+
+    /// The underlying reader, unless a `BinaryInput` is still alive
+    pub(super) fn into_reader(self) -> Option<Box<dyn BufRead + 'a>> {
+        Rc::try_unwrap(self.reader)
+            .ok()
+            .map(|reader| reader.into_inner().reader)
+    }
+}
+
+fn missing_field(field: &str) -> Box<dyn Error> {
+    Box::new(err_msg(format!(
+        "Error decoding JSON request: missing field `{}`",
+        field
+    )))
+}
+
+// Parses request objects directly from the stream (since a line may not fit in memory)
+struct RequestReader<'a> {
+    reader: Box<dyn BufRead + 'a>,
+    // Incremented for each request, so the input of an earlier request can't read a later one
+    request: u64,
+    // Whether the reader is inside the `data` string of the current request
+    in_data: bool,
+}
+
+impl<'a> RequestReader<'a> {
+    // Start reading the next request object, returning false at the end of the stream
+    fn start_request(&mut self) -> io::Result<bool> {
+        self.request += 1;
+        self.in_data = false;
+        self.skip_spaces()?;
+        match self.peek()? {
+            None => Ok(false),
+            Some(_) => self.expect(b'{').map(|_| true),
+        }
+    }
+
+    // Read members of the request object until its `data` string can be streamed or the object ends
+    fn read_members(&mut self, env: &mut Envelope) -> io::Result<()> {
+        loop {
+            self.skip_spaces()?;
+            match self.peek()? {
+                Some(b'}') => {
+                    self.consume(1);
+                    return self.end_line();
+                }
+                Some(b',') if env.members > 0 => {
+                    self.consume(1);
+                    self.skip_spaces()?;
+                }
+                _ if env.members > 0 => return Err(invalid("expected `,` or `}`")),
+                _ => (),
+            }
+
+            let key: String = self.read_json()?;
+            self.skip_spaces()?;
+            self.expect(b':')?;
+            self.skip_spaces()?;
+            env.members += 1;
+            match &*key {
+                "data" => {
+                    let streamable = match env.content_type {
+                        Some(ref content_type) => {
+                            content_type == "text" || content_type == "binary"
+                        }
+                        None => false,
+                    };
+                    if streamable && !env.streaming && self.peek()? == Some(b'"') {
+                        self.consume(1);
+                        self.in_data = true;
+                        env.streaming = true;
+                        return Ok(());
+                    }
+                    let mut raw = Vec::new();
+                    self.read_value(&mut raw)?;
+                    env.data = Some(raw);
+                }
+                "content_type" => env.content_type = Some(self.read_json()?),
+                "request_id" => env.request_id = self.read_json()?,
+                _ => {
+                    self.read_json::<IgnoredAny>()?;
+                }
+            }
+        }
+    }
+
+    // Append up to about `max` bytes of the unescaped `data` string to `out`, returning false once it ends
+    fn read_data(&mut self, out: &mut Vec<u8>, max: usize) -> io::Result<bool> {
+        while self.in_data && out.len() < max {
+            let (n, special) = {
+                let buf = self.reader.fill_buf()?;
+                if buf.is_empty() {
+                    return Err(invalid("EOF while parsing a string"));
+                }
+                let end = buf
+                    .iter()
+                    .position(|&b| b == b'"' || b == b'\\' || b < 0x20);
+                let n = cmp::min(end.unwrap_or(buf.len()), max - out.len());
+                out.extend_from_slice(&buf[..n]);
+                (n, end.filter(|&end| end == n).map(|end| buf[end]))
+            };
+            self.consume(n);
+            match special {
+                Some(b'"') => {
+                    self.consume(1);
+                    self.in_data = false;
+                }
+                Some(b'\\') => {
+                    self.consume(1);
+                    self.read_escape(out)?;
+                }
+                Some(_) => return Err(invalid("control character in string")),
+                None => (),
+            }
+        }
+        Ok(self.in_data)
+    }
+
+    // Skip the rest of the `data` string
+    fn skip_data(&mut self, scratch: &mut Vec<u8>) -> io::Result<()> {
+        while self.in_data {
+            scratch.clear();
+            self.read_data(scratch, DECODE_CHUNK_SIZE)?;
+        }
+        Ok(())
+    }
+
+    fn read_escape(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        let unescaped = match self.next_byte()? {
+            b'"' => b'"',
+            b'\\' => b'\\',
+            b'/' => b'/',
+            b'b' => 0x08,
+            b'f' => 0x0c,
+            b'n' => b'\n',
+            b'r' => b'\r',
+            b't' => b'\t',
+            b'u' => {
+                let mut code = self.read_hex()?;
+                if code >= 0xD800 && code < 0xDC00 {
+                    self.expect(b'\\')?;
+                    self.expect(b'u')?;
+                    let low = self.read_hex()?;
+                    if low < 0xDC00 || low >= 0xE000 {
+                        return Err(invalid("invalid unicode escape"));
+                    }
+                    code = 0x10000 + ((code - 0xD800) << 10) + (low - 0xDC00);
+                }
+                let c =
+                    std::char::from_u32(code).ok_or_else(|| invalid("invalid unicode escape"))?;
+                let mut encoded = [0; 4];
+                out.extend_from_slice(c.encode_utf8(&mut encoded).as_bytes());
+                return Ok(());
+            }
+            _ => return Err(invalid("invalid escape")),
+        };
+        out.push(unescaped);
+        Ok(())
+    }
+
+    fn read_hex(&mut self) -> io::Result<u32> {
+        let mut code = 0;
+        for _ in 0..4 {
+            let digit = (self.next_byte()? as char)
+                .to_digit(16)
+                .ok_or_else(|| invalid("invalid unicode escape"))?;
+            code = code * 16 + digit;
+        }
+        Ok(code)
+    }
+
+    // Read a single JSON value
+    fn read_json<T: serde::de::DeserializeOwned>(&mut self) -> io::Result<T> {
+        let mut raw = Vec::new();
+        self.read_value(&mut raw)?;
+        serde_json::from_slice(&raw).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    // Append the raw JSON of a single value to `out` (which is only validated when it is parsed)
+    fn read_value(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        let mut depth = 0;
+        loop {
+            let byte = match self.peek()? {
+                Some(byte) => byte,
+                None => return Ok(()),
+            };
+            match byte {
+                b'"' => self.read_raw_string(out)?,
+                b'{' | b'[' => depth += 1,
+                b'}' | b']' if depth > 0 => depth -= 1,
+                b',' | b'}' | b']' | b' ' | b'\t' | b'\r' if depth == 0 => return Ok(()),
+                b'\n' => return Ok(()),
+                _ => (),
+            }
+            if byte != b'"' {
+                out.push(byte);
+                self.consume(1);
+            }
+            if depth == 0 && (byte == b'"' || byte == b'}' || byte == b']') {
+                return Ok(());
+            }
+        }
+    }
+
+    // Append a string with its quotes and escapes to `out`
+    fn read_raw_string(&mut self, out: &mut Vec<u8>) -> io::Result<()> {
+        out.push(b'"');
+        self.consume(1);
+        loop {
+            let (n, end) = {
+                let buf = self.reader.fill_buf()?;
+                if buf.is_empty() {
+                    return Ok(());
+                }
+                let end = buf
+                    .iter()
+                    .position(|&b| b == b'"' || b == b'\\' || b == b'\n');
+                let n = end.unwrap_or(buf.len());
+                out.extend_from_slice(&buf[..n]);
+                (n, end.map(|end| buf[end]))
+            };
+            self.consume(n);
+            match end {
+                Some(b'"') => {
+                    out.push(b'"');
+                    self.consume(1);
+                    return Ok(());
+                }
+                Some(b'\\') => {
+                    out.push(b'\\');
+                    self.consume(1);
+                    match self.peek()? {
+                        Some(b'\n') | None => return Ok(()),
+                        Some(b) => {
+                            out.push(b);
+                            self.consume(1);
+                        }
+                    }
+                }
+                Some(_) => return Ok(()),
+                None => (),
+            }
+        }
+    }
+
+    // Expect the end of the line after a request object
+    fn end_line(&mut self) -> io::Result<()> {
+        self.skip_spaces()?;
+        match self.peek()? {
+            None => Ok(()),
+            Some(b'\n') => {
+                self.consume(1);
+                Ok(())
+            }
+            Some(_) => Err(invalid("trailing characters")),
+        }
+    }
+
+    // Discard the rest of the current line, e.g. after a malformed request
+    fn skip_line(&mut self) {
+        self.in_data = false;
+        let mut line = Vec::new();
+        loop {
+            line.clear();
+            match self
+                .reader
+                .by_ref()
+                .take(DECODE_CHUNK_SIZE as u64)
+                .read_until(b'\n', &mut line)
+            {
+                Ok(n) if n > 0 && line.last() != Some(&b'\n') => (),
+                _ => return,
+            }
+        }
+    }
+
+    // Skip whitespace within a line (since a newline ends a request)
+    fn skip_spaces(&mut self) -> io::Result<()> {
+        while let Some(b' ') | Some(b'\t') | Some(b'\r') = self.peek()? {
+            self.consume(1);
+        }
+        Ok(())
+    }
+
+    fn expect(&mut self, byte: u8) -> io::Result<()> {
+        match self.peek()? {
+            Some(b) if b == byte => {
+                self.consume(1);
+                Ok(())
+            }
+            Some(_) => Err(invalid(&format!("expected `{}`", byte as char))),
+            None => Err(invalid("unexpected EOF")),
+        }
+    }
+
+    fn next_byte(&mut self) -> io::Result<u8> {
+        match self.peek()? {
+            Some(b) => {
+                self.consume(1);
+                Ok(b)
+            }
+            None => Err(invalid("unexpected EOF")),
+        }
+    }
+
+    fn peek(&mut self) -> io::Result<Option<u8>> {
+        loop {
+            match self.reader.fill_buf() {
+                Ok(buf) => return Ok(buf.first().cloned()),
+                Err(ref err) if err.kind() == io::ErrorKind::Interrupted => (),
+                Err(err) => return Err(err),
+            }
+        }
+    }
+
+    fn consume(&mut self, n: usize) {
+        self.reader.consume(n);
+    }
+}
+
+fn invalid(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+/// Base64-encoded handler output, spooled to a temporary file so that
+/// a response is only written once the output has been read completely
+pub(super) struct Spool {
+    file: File,
+    path: PathBuf,
+}
+
+impl Spool {
+    fn new() -> io::Result<Spool> {
+        let count = SPOOL_COUNT.fetch_add(1, Ordering::Relaxed);
+        let path = env::temp_dir().join(format!("algorithmia-output-{}-{}", process::id(), count));
+        let file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create_new(true)
+            .open(&path)?;
+        Ok(Spool {
+            file: file,
+            path: path,
+        })
+    }
+}
+
+impl Drop for Spool {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+// Base64-encode `output` into a spool file
+pub(super) fn spool_output<R: Read>(mut output: R) -> Result<Spool, Box<dyn Error>> {
+    let mut spool = Spool::new().context("Cannot create file for handler output")?;
+    {
+        let mut w = BufWriter::new(&spool.file);
+        {
+            let mut encoder = base64::write::EncoderWriter::new(&mut w, base64::STANDARD);
+            io::copy(&mut output, &mut encoder).context("Cannot read handler output")?;
+            encoder.finish().context("Cannot write handler output")?;
+        }
+        w.flush().context("Cannot write handler output")?;
+    }
+    spool
+        .file
+        .seek(SeekFrom::Start(0))
+        .context("Cannot read handler output")?;
+    Ok(spool)
+}
+
+// Write a binary success response with the spooled output as its result
+pub(super) fn write_stream_response<W: Write>(
+    mut w: W,
+    mut spool: Spool,
+    request_id: Option<&str>,
+) -> Result<(), crate::error::Error> {
+    // Serialize the envelope with a null result, then splice the encoded output in its place
+    let mut success = AlgoSuccess::new(Value::Null, "binary");
+    success.request_id = request_id.map(String::from);
+    let envelope = serde_json::to_string(&success).context("Failed to encode JSON")?;
+    debug_assert!(envelope.starts_with(NULL_RESULT));
+    let rest = &envelope[NULL_RESULT.len()..];
+
+    w.write_all(br#"{"result":""#)
+        .context("Cannot write response")?;
+    io::copy(&mut spool.file, &mut w).context("Cannot write response")?;
+    w.write_all(b"\"").context("Cannot write response")?;
+    w.write_all(rest.as_bytes())
+        .context("Cannot write response")?;
+    w.write_all(b"\n").context("Cannot write response")?;
+    w.flush().context("Cannot write response")?;
+    Ok(())
+}
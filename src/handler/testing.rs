@@ -0,0 +1,332 @@
+//! Utilities for testing handlers without the Algorithmia platform
+//!
+//! [`invoke`](fn.invoke.html) sends a single request through the same path as
+//! [`handler::run`](../fn.run.html): decoding the request envelope, converting the input,
+//! calling `apply`, and encoding the response envelope.
+//!
+//! ```rust
+//! use algorithmia::handler::testing::{invoke, RequestEnvelope};
+//!
+//! fn apply(name: String) -> Result<String, String> {
+//!     Ok(format!("Hello {}", name))
+//! }
+//!
+//! let response = invoke(apply, RequestEnvelope::text("world"));
+//! assert_eq!(response.result().unwrap(), "Hello world");
+//! ```
+
+use super::{call_apply, guard_apply, process_line, AlgoOutput};
+use crate::algo::TryFrom;
+use crate::prelude::AlgoIo;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use std::error::Error;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const REQUEST_SUFFIX: &'static str = ".request.json";
+const RESPONSE_SUFFIX: &'static str = ".response.json";
+
+/// A request as sent to an algorithm by the platform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RequestEnvelope {
+    /// Content type of `data`: `text`, `json`, or `binary`
+    pub content_type: String,
+    /// Request input (base64-encoded for `binary`)
+    pub data: Value,
+    /// Identifier that is echoed in the response
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
+}
+
+/// A response as returned by an algorithm to the platform
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResponseEnvelope(Value);
+
+impl RequestEnvelope {
+    /// Instantiate a text request
+    pub fn text<S: Into<String>>(text: S) -> RequestEnvelope {
+        RequestEnvelope::new("text", Value::String(text.into()))
+    }
+
+    /// Instantiate a JSON request
+    pub fn json<J: Into<Value>>(json: J) -> RequestEnvelope {
+        RequestEnvelope::new("json", json.into())
+    }
+
+    /// Instantiate a binary request
+    pub fn binary<B: AsRef<[u8]>>(bytes: B) -> RequestEnvelope {
+        RequestEnvelope::new("binary", Value::String(base64::encode(bytes.as_ref())))
+    }
+
+    /// Builder method to set the request identifier
+    pub fn with_request_id<S: Into<String>>(mut self, request_id: S) -> RequestEnvelope {
+        self.request_id = Some(request_id.into());
+        self
+    }
+
+    fn new(content_type: &str, data: Value) -> RequestEnvelope {
+        RequestEnvelope {
+            content_type: content_type.into(),
+            data: data,
+            request_id: None,
+        }
+    }
+}
+
+impl ResponseEnvelope {
+    /// Returns true if the request succeeded
+    pub fn is_success(&self) -> bool {
+        self.0.get("result").is_some()
+    }
+
+    /// The result of a successful request (base64-encoded for binary output)
+    pub fn result(&self) -> Option<&Value> {
+        self.0.get("result")
+    }
+
+    /// The result of a successful request with binary output
+    pub fn binary(&self) -> Option<Vec<u8>> {
+        if self.content_type() != Some("binary") {
+            return None;
+        }
+        self.result()
+            .and_then(Value::as_str)
+            .and_then(|encoded| base64::decode(encoded).ok())
+    }
+
+    /// The content type of a successful result: `text`, `json`, or `binary`
+    pub fn content_type(&self) -> Option<&str> {
+        self.metadata()
+            .and_then(|metadata| metadata.get("content_type"))
+            .and_then(Value::as_str)
+    }
+
+    /// The metadata of a successful request
+    pub fn metadata(&self) -> Option<&Value> {
+        self.0.get("metadata")
+    }
+
+    /// The error of a failed request
+    pub fn error(&self) -> Option<&Value> {
+        self.0.get("error")
+    }
+
+    /// The error type (e.g. `InputError`) of a failed request
+    pub fn error_type(&self) -> Option<&str> {
+        self.error()
+            .and_then(|err| err.get("error_type"))
+            .and_then(Value::as_str)
+    }
+
+    /// The whole response as JSON
+    pub fn as_json(&self) -> &Value {
+        &self.0
+    }
+}
+
+/// Process a single request with `apply`, returning the response that the platform would receive
+///
+/// Panics in `apply` are reported as failed requests, as they are by `handler::run`.
+pub fn invoke<F, IN, OUT, E, E2>(mut apply: F, request: RequestEnvelope) -> ResponseEnvelope
+where
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    let line = serde_json::to_string(&request).expect("Failed to encode request");
+    let output_json = process_line(Ok(line), |input, _| {
+        guard_apply(true, || call_apply(&mut apply, input))
+    });
+    ResponseEnvelope(serde_json::from_str(&output_json).expect("Failed to decode response"))
+}
+
+/// Check `apply` against every request fixture in `dir`
+///
+/// Each `<name>.request.json` file contains a [`RequestEnvelope`](struct.RequestEnvelope.html),
+/// and the matching `<name>.response.json` file contains the expected response.
+/// Fields that vary between runs can be left out of the expected response: only the fields
+/// that it contains are compared (e.g. an `error` with just an `error_type` matches any
+/// error of that type, regardless of the message or stacktrace).
+///
+/// ```rust,no_run
+/// use algorithmia::handler::testing;
+///
+/// fn apply(name: String) -> Result<String, String> {
+///     Ok(format!("Hello {}", name))
+/// }
+///
+/// #[test]
+/// fn test_fixtures() {
+///     testing::run_fixtures(concat!(env!("CARGO_MANIFEST_DIR"), "/tests/fixtures"), apply);
+/// }
+/// ```
+///
+/// # Panics
+///
+/// Panics with a description of every fixture that doesn't match, has no expected response,
+/// or cannot be read. It also panics if `dir` contains no request fixtures.
+pub fn run_fixtures<P, F, IN, OUT, E, E2>(dir: P, mut apply: F)
+where
+    P: AsRef<Path>,
+    F: FnMut(IN) -> Result<OUT, E>,
+    IN: TryFrom<AlgoIo, Error = E2>,
+    OUT: Into<AlgoOutput>,
+    E: Into<Box<dyn Error>>,
+    E2: Into<Box<dyn Error>>,
+{
+    let dir = dir.as_ref();
+    let fixtures = request_fixtures(dir)
+        .unwrap_or_else(|err| panic!("Cannot read fixtures in '{}': {}", dir.display(), err));
+    if fixtures.is_empty() {
+        panic!(
+            "No *{} fixtures found in '{}'",
+            REQUEST_SUFFIX,
+            dir.display()
+        );
+    }
+
+    let mut failures = Vec::new();
+    for (name, request_path) in fixtures {
+        let response_path = dir.join(format!("{}{}", name, RESPONSE_SUFFIX));
+        let fixture = read_json::<RequestEnvelope>(&request_path)
+            .and_then(|request| Ok((request, read_json::<Value>(&response_path)?)));
+        let (request, expected) = match fixture {
+            Ok(fixture) => fixture,
+            Err(err) => {
+                failures.push(format!("{}: {}", name, err));
+                continue;
+            }
+        };
+
+        let actual = invoke(&mut apply, request);
+        if !json_contains(actual.as_json(), &expected) {
+            failures.push(format!(
+                "{}: response does not match {}\n  expected: {}\n  actual:   {}",
+                name,
+                response_path.display(),
+                expected,
+                actual.as_json()
+            ));
+        }
+    }
+
+    if !failures.is_empty() {
+        panic!(
+            "{} handler fixture(s) failed:\n{}",
+            failures.len(),
+            failures.join("\n")
+        );
+    }
+}
+
+// Fixture names and request paths, sorted by name
+fn request_fixtures(dir: &Path) -> Result<Vec<(String, PathBuf)>, Box<dyn Error>> {
+    let mut fixtures = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = match path.file_name().and_then(|name| name.to_str()) {
+            Some(file_name) if file_name.ends_with(REQUEST_SUFFIX) => {
+                file_name[..file_name.len() - REQUEST_SUFFIX.len()].to_owned()
+            }
+            _ => continue,
+        };
+        fixtures.push((name, path));
+    }
+    fixtures.sort();
+    Ok(fixtures)
+}
+
+fn read_json<T: for<'de> Deserialize<'de>>(path: &Path) -> Result<T, Box<dyn Error>> {
+    let contents = fs::read_to_string(path)
+        .map_err(|err| format!("cannot read '{}': {}", path.display(), err))?;
+    let json = serde_json::from_str(&contents)
+        .map_err(|err| format!("cannot decode '{}': {}", path.display(), err))?;
+    Ok(json)
+}
+
+// Returns true if every field in `expected` has the same value in `actual`
+fn json_contains(actual: &Value, expected: &Value) -> bool {
+    match (actual, expected) {
+        (Value::Object(actual), Value::Object(expected)) => {
+            expected.iter().all(|(key, expected)| {
+                actual
+                    .get(key)
+                    .map_or(false, |actual| json_contains(actual, expected))
+            })
+        }
+        _ => actual == expected,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use std::env;
+
+    fn apply(n: u32) -> Result<u32, String> {
+        match n {
+            0 => Err("zero".into()),
+            n => Ok(n * 2),
+        }
+    }
+
+    #[test]
+    fn test_invoke() {
+        let response = invoke(apply, RequestEnvelope::json(21).with_request_id("a"));
+        assert!(response.is_success());
+        assert_eq!(response.result().unwrap(), 42);
+        assert_eq!(response.content_type(), Some("json"));
+        assert_eq!(response.as_json()["request_id"], "a");
+
+        let response = invoke(apply, RequestEnvelope::text("abc"));
+        assert_eq!(response.error_type(), Some("InputError"));
+
+        let echo = |bytes: crate::algo::ByteVec| Ok::<_, String>(bytes);
+        let response = invoke(echo, RequestEnvelope::binary(&[1u8, 2, 3]));
+        assert_eq!(response.binary(), Some(vec![1, 2, 3]));
+    }
+
+    #[test]
+    fn test_run_fixtures() {
+        let dir = env::temp_dir().join(format!("algorithmia-fixtures-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let fixtures = [
+            (
+                "double",
+                json!({"content_type": "json", "data": 4}),
+                json!({"result": 8}),
+            ),
+            (
+                "zero",
+                json!({"content_type": "json", "data": 0}),
+                json!({"error": {"error_type": "AlgorithmError", "message": "zero"}}),
+            ),
+        ];
+        for (name, request, response) in &fixtures {
+            fs::write(
+                dir.join(format!("{}.request.json", name)),
+                request.to_string(),
+            )
+            .unwrap();
+            fs::write(
+                dir.join(format!("{}.response.json", name)),
+                response.to_string(),
+            )
+            .unwrap();
+        }
+        run_fixtures(&dir, apply);
+
+        fs::write(
+            dir.join("double.response.json"),
+            json!({"result": 9}).to_string(),
+        )
+        .unwrap();
+        let result = std::panic::catch_unwind(|| run_fixtures(&dir, apply));
+        fs::remove_dir_all(&dir).unwrap();
+        assert!(result.is_err());
+    }
+}
@@ -34,6 +34,57 @@ pub mod data;
 #[cfg(feature = "handler")]
 pub mod handler;
 
+/// Generate `main` for an algorithm [feature = "macros"]
+///
+/// Applied to an `apply` function, `main` runs it with
+/// [`handler::run`](handler/fn.run.html) (or
+/// [`handler::run_with_context`](handler/fn.run_with_context.html) if it also takes a `&Context`):
+///
+/// ```rust,no_run
+/// #[algorithmia::entrypoint]
+/// fn apply(name: String) -> Result<String, String> {
+///     Ok(format!("Hello {}", name))
+/// }
+/// ```
+///
+/// Applied to an `impl` block, `load` is called once to create the algorithm's state,
+/// and `apply` is called with it for each request, as with
+/// [`handler::load_and_run`](handler/fn.load_and_run.html):
+///
+/// ```rust,no_run
+/// use std::error::Error;
+///
+/// struct Model {
+///     weights: Vec<u8>,
+/// }
+///
+/// #[algorithmia::entrypoint]
+/// impl Model {
+///     fn load() -> Result<Model, Box<dyn Error>> {
+///         Ok(Model { weights: std::fs::read("weights.bin")? })
+///     }
+///
+///     fn apply(&mut self, text: String) -> Result<usize, String> {
+///         Ok(text.len() * self.weights.len())
+///     }
+/// }
+/// ```
+///
+/// `apply` must return `Result<T, E>`, and its types are checked where they are written:
+/// the input must implement `TryFrom<AlgoIo>`, the output `Into<AlgoIo>` (or `Into<AlgoOutput>`),
+/// and the error `Into<Box<dyn Error>>`.
+///
+/// With `#[algorithmia::entrypoint(schema)]`, running the binary with `--schema` prints the
+/// JSON schema of the input and output (see [`handler::schema`](handler/fn.schema.html)),
+/// which requires the `schema` feature.
+#[cfg(feature = "macros")]
+pub use algorithmia_macros::entrypoint;
+
+// The dependency is renamed so that the `tracing` feature can enable `tracing-subscriber` too
+// (without the `dep:` feature syntax that requires a newer Cargo)
+#[cfg(feature = "tracing")]
+extern crate tracing_crate as tracing;
+
 use crate::client::ApiAuth;
 use crate::error::Error;
 pub use reqwest::Body;
@@ -51,6 +102,8 @@ pub mod prelude {
 }
 
 mod client;
+#[cfg(any(feature = "gzip", feature = "zstd"))]
+mod compression;
 mod version;
 
 const DEFAULT_API_BASE_URL: &'static str = "https://api.algorithmia.com";